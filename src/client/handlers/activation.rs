@@ -0,0 +1,46 @@
+use sctk::{
+    activation::{ActivationHandler, RequestData, RequestDataExt},
+    reexports::client::protocol::{wl_seat::WlSeat, wl_surface::WlSurface},
+};
+use smithay::reexports::{
+    wayland_protocols::xdg::activation::v1::server::xdg_activation_token_v1::XdgActivationTokenV1,
+    wayland_server::Resource,
+};
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+/// a host activation token requested for an embedded activation token
+#[derive(Debug)]
+pub struct EmbeddedTokenRequest {
+    /// the request sent to the host
+    pub data: RequestData,
+    /// the embedded token which receives the host token
+    pub token: XdgActivationTokenV1,
+}
+
+impl RequestDataExt for EmbeddedTokenRequest {
+    fn app_id(&self) -> Option<&str> {
+        self.data.app_id()
+    }
+
+    fn seat_and_serial(&self) -> Option<(&WlSeat, u32)> {
+        self.data.seat_and_serial()
+    }
+
+    fn surface(&self) -> Option<&WlSurface> {
+        self.data.surface()
+    }
+}
+
+impl<W: WrapperSpace> ActivationHandler for GlobalState<W> {
+    type RequestData = EmbeddedTokenRequest;
+
+    fn new_token(&mut self, token: String, data: &Self::RequestData) {
+        // the embedded client hands the host token to the app it launches
+        if data.token.is_alive() {
+            data.token.done(token);
+        }
+    }
+}
+
+sctk::delegate_activation!(@<W: WrapperSpace + 'static> GlobalState<W>, EmbeddedTokenRequest);
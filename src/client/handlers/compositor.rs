@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use std::time::Instant;
+
 use sctk::{
     compositor::CompositorHandler,
     reexports::client::{protocol::wl_surface, Connection, QueueHandle},
@@ -27,8 +29,16 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
         surface: &wl_surface::WlSurface,
         time: u32,
     ) {
-        // TODO proxied layer surfaces
-        if let Some(seat) = self.server_state.seats.iter_mut().find(|s| {
+        self.client_state.last_frame = Some((time, Instant::now()));
+        if let Some((_, _, s_layer, c_layer, ..)) = self
+            .client_state
+            .proxied_layer_surfaces
+            .iter()
+            .find(|s| s.3.wl_surface() == surface)
+        {
+            self.client_state
+                .send_frames(c_layer.wl_surface(), s_layer.wl_surface(), time);
+        } else if let Some(seat) = self.server_state.seats.iter_mut().find(|s| {
             s.client
                 .dnd_icon
                 .iter()
@@ -14,6 +14,8 @@ use sctk::{
     },
     seat::pointer::{PointerEvent, PointerEventKind, PointerHandler},
 };
+#[cfg(feature = "xwayland")]
+use smithay::wayland::selection::SelectionTarget;
 use smithay::{
     input::pointer::GrabStartData,
     reexports::wayland_server::{protocol::wl_data_device_manager::DndAction, Resource},
@@ -23,7 +25,10 @@ use smithay::{
     },
 };
 
-use crate::{client_state::FocusStatus, shared_state::GlobalState, space::WrapperSpace};
+use crate::{
+    client_state::FocusStatus, server::handlers::SelectionOrigin, shared_state::GlobalState,
+    space::WrapperSpace,
+};
 
 impl<W: WrapperSpace> DataDeviceHandler for GlobalState<W> {
     fn selection(
@@ -66,9 +71,11 @@ impl<W: WrapperSpace> DataDeviceHandler for GlobalState<W> {
         set_data_device_selection(
             &self.server_state.display_handle,
             &seat.server.seat,
-            mime_types,
-            (),
-        )
+            mime_types.clone(),
+            SelectionOrigin::Host,
+        );
+        #[cfg(feature = "xwayland")]
+        self.x11_new_selection(SelectionTarget::Clipboard, Some(mime_types));
     }
 
     fn enter(
@@ -155,7 +162,7 @@ impl<W: WrapperSpace> DataDeviceHandler for GlobalState<W> {
                 self,
                 SERIAL_COUNTER.next_serial(),
                 Some(GrabStartData {
-                    focus: server_focus.map(|f| (f.surface, f.s_pos)),
+                    focus: server_focus.map(|f| (f.surface, f.s_pos.to_f64())),
                     button: 0x110, // assume left button for now, maybe there is another way..
                     location: (x, y).into(),
                 }),
@@ -8,6 +8,14 @@ use sctk::reexports::client::protocol::wl_data_source::WlDataSource;
 use sctk::seat::pointer::{PointerEvent, PointerEventKind, PointerHandler};
 use smithay::reexports::wayland_server::protocol::wl_data_device_manager::DndAction;
 use smithay::utils::SERIAL_COUNTER;
+#[cfg(feature = "xwayland")]
+use {
+    crate::server::handlers::SelectionOrigin,
+    smithay::wayland::selection::{
+        data_device::current_data_device_selection_userdata, SelectionTarget,
+    },
+    std::os::fd::{FromRawFd, OwnedFd},
+};
 
 impl<W: WrapperSpace> DataSourceHandler for GlobalState<W> {
     fn send_request(
@@ -50,6 +58,15 @@ impl<W: WrapperSpace> DataSourceHandler for GlobalState<W> {
                 dnd_source.send(mime, fd.as_fd());
             }
         } else {
+            #[cfg(feature = "xwayland")]
+            if current_data_device_selection_userdata::<Self>(&seat.server.seat).as_deref()
+                == Some(&SelectionOrigin::Xwayland)
+            {
+                // the embedded selection is owned by an X11 client
+                let fd = unsafe { OwnedFd::from_raw_fd(fd.into_raw_fd()) };
+                self.x11_send_selection(SelectionTarget::Clipboard, mime, fd);
+                return;
+            }
             if let Some(selection) = seat.server.selection_source.as_ref() {
                 selection.send(mime, fd.as_fd());
             }
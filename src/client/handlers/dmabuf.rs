@@ -0,0 +1,62 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use sctk::{
+    delegate_dmabuf,
+    dmabuf::{DmabufFeedback, DmabufHandler, DmabufState},
+    reexports::{
+        client::{protocol::wl_buffer, Connection, QueueHandle},
+        protocols::wp::linux_dmabuf::zv1::client::{
+            zwp_linux_buffer_params_v1, zwp_linux_dmabuf_feedback_v1,
+        },
+    },
+};
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+impl<W: WrapperSpace> DmabufHandler for GlobalState<W> {
+    fn dmabuf_state(&mut self) -> &mut DmabufState {
+        &mut self.client_state.dmabuf_state
+    }
+
+    fn dmabuf_feedback(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _proxy: &zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1,
+        feedback: DmabufFeedback,
+    ) {
+        self.client_state.dmabuf_feedback = Some(feedback);
+        // the host may resend feedback at any time, so keep the embedded global in sync
+        self.update_dmabuf_global();
+    }
+
+    fn created(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _params: &zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1,
+        _buffer: wl_buffer::WlBuffer,
+    ) {
+        // the wrapper never creates dmabuf backed buffers on the host
+    }
+
+    fn failed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _params: &zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1,
+    ) {
+        // the wrapper never creates dmabuf backed buffers on the host
+    }
+
+    fn released(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _buffer: &wl_buffer::WlBuffer,
+    ) {
+        // the wrapper never creates dmabuf backed buffers on the host
+    }
+}
+
+delegate_dmabuf!(@<W: WrapperSpace + 'static> GlobalState<W>);
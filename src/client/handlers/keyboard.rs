@@ -1,18 +1,19 @@
 use std::time::Instant;
 
 use crate::{
-    client_state::FocusStatus, server_state::SeatPair, shared_state::GlobalState,
-    space::WrapperSpace,
+    client_state::FocusStatus, focus::FocusReason, keybindings::KeyBinding, server_state::SeatPair,
+    shared_state::GlobalState, space::WrapperSpace,
 };
 use sctk::{
     delegate_keyboard,
+    reexports::client::protocol::wl_surface::WlSurface,
     seat::keyboard::{KeyboardHandler, Keysym, RepeatInfo},
     shell::WaylandSurface,
 };
 use smithay::{
     backend::input::KeyState,
     input::keyboard::{
-        xkb::{Keymap, KeymapFormat},
+        xkb::{Keycode, Keymap, KeymapFormat},
         FilterResult,
     },
     utils::SERIAL_COUNTER,
@@ -30,16 +31,16 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
         _keysyms: &[Keysym],
     ) {
         let _ = _keysyms;
-        let (seat_name, kbd) = if let Some((name, Some(kbd))) = self
+        let seat_name = if let Some(name) = self
             .server_state
             .seats
             .iter()
             .find(|SeatPair { client, .. }| {
                 client.kbd.as_ref().map(|k| k == keyboard).unwrap_or(false)
             })
-            .map(|seat| (seat.name.as_str(), seat.server.seat.get_keyboard()))
+            .map(|seat| seat.name.clone())
         {
-            (name.to_string(), kbd)
+            name
         } else {
             return;
         };
@@ -69,12 +70,13 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
                 }
             });
 
-        if let Some(s_surface) = s_surface {
-            kbd.set_focus(self, Some(s_surface), SERIAL_COUNTER.next_serial());
-        } else {
-            let s = self.space.keyboard_enter(&seat_name, surface.clone());
-            kbd.set_focus(self, s, SERIAL_COUNTER.next_serial());
-        }
+        // keys which were held on a destroyed host surface are still pressed
+        self.release_pressed_keys(&seat_name);
+        let s_surface = match s_surface {
+            Some(s_surface) => Some(s_surface),
+            None => self.space.keyboard_enter(&seat_name, surface.clone()),
+        };
+        self.set_keyboard_focus(&seat_name, s_surface, FocusReason::Enter);
     }
 
     fn leave(
@@ -85,16 +87,16 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
         surface: &sctk::reexports::client::protocol::wl_surface::WlSurface,
         _serial: u32,
     ) {
-        let (name, kbd) = if let Some((name, Some(kbd))) = self
+        let name = if let Some(name) = self
             .server_state
             .seats
             .iter()
             .find(|SeatPair { client, .. }| {
                 client.kbd.as_ref().map(|k| k == keyboard).unwrap_or(false)
             })
-            .map(|seat| (seat.name.as_str(), seat.server.seat.get_keyboard()))
+            .map(|seat| seat.name.clone())
         {
-            (name.to_string(), kbd)
+            name
         } else {
             return;
         };
@@ -120,7 +122,9 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
                 self.space.keyboard_leave(&name, Some(surface.clone()));
             }
         }
-        kbd.set_focus(self, None, SERIAL_COUNTER.next_serial());
+        // the host won't send releases for keys which are held after the focus leaves
+        self.release_pressed_keys(&name);
+        self.set_keyboard_focus(&name, None, FocusReason::Leave);
     }
 
     fn press_key(
@@ -131,52 +135,52 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
         serial: u32,
         event: sctk::seat::keyboard::KeyEvent,
     ) {
-        let (seat_name, kbd) = if let Some((name, Some(kbd), last_key_pressed)) = self
-            .server_state
-            .seats
-            .iter_mut()
-            .find(|SeatPair { client, .. }| {
-                client.kbd.as_ref().map(|k| k == keyboard).unwrap_or(false)
-            })
-            .map(|seat| {
-                (
-                    seat.name.as_str(),
-                    seat.server.seat.get_keyboard(),
-                    &mut seat.client.last_key_press,
-                )
-            }) {
-            *last_key_pressed = (serial, event.time);
-            (name.to_string(), kbd)
+        let (seat_name, kbd) = if let Some(seat) =
+            self.server_state
+                .seats
+                .iter_mut()
+                .find(|SeatPair { client, .. }| {
+                    client.kbd.as_ref().map(|k| k == keyboard).unwrap_or(false)
+                }) {
+            seat.client.last_key_press = (serial, event.time);
+            if !seat.client.pressed_keys.contains(&event.raw_code) {
+                seat.client.pressed_keys.push(event.raw_code);
+            }
+            match seat.server.seat.get_keyboard() {
+                Some(kbd) => (seat.name.clone(), kbd),
+                None => return,
+            }
         } else {
             return;
         };
-        let c_kbd_focus = {
-            let c_focused_surface = self.client_state.focused_surface.borrow_mut();
-            c_focused_surface.iter().find_map(|f| {
-                if f.1 == seat_name {
-                    Some(f.0.clone())
-                } else {
-                    None
-                }
-            })
-        };
-
-        if let Some(c_focus) = c_kbd_focus {
-            self.client_state.last_key_pressed.push((
-                seat_name,
-                (event.raw_code, event.time),
-                c_focus,
-            ))
-        }
 
-        let _ = kbd.input::<(), _>(
+        let binding = kbd.input::<KeyBinding, _>(
             self,
-            event.raw_code,
+            keycode(event.raw_code),
             KeyState::Pressed,
             SERIAL_COUNTER.next_serial(),
             event.time,
-            move |_, _modifiers, _keysym| FilterResult::Forward,
+            move |state, modifiers, keysym| match state.keybindings.find(modifiers, &keysym) {
+                Some(binding) => FilterResult::Intercept(binding.clone()),
+                None => FilterResult::Forward,
+            },
         );
+
+        if let Some(binding) = binding {
+            // the key was never pressed for embedded clients, so its release is intercepted too
+            if let Some(seat) = self
+                .server_state
+                .seats
+                .iter_mut()
+                .find(|s| s.name == seat_name)
+            {
+                seat.client
+                    .pressed_keys
+                    .retain(|code| *code != event.raw_code);
+                seat.client.intercepted_keys.push(event.raw_code);
+            }
+            self.space.keybinding(&seat_name, &binding);
+        }
     }
 
     fn release_key(
@@ -187,31 +191,41 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
         _serial: u32,
         event: sctk::seat::keyboard::KeyEvent,
     ) {
-        let (name, kbd) = if let Some((name, Some(kbd))) = self
-            .server_state
-            .seats
-            .iter()
-            .find(|SeatPair { client, .. }| {
-                client.kbd.as_ref().map(|k| k == keyboard).unwrap_or(false)
-            })
-            .map(|seat| (seat.name.as_str(), seat.server.seat.get_keyboard()))
-        {
-            (name.to_string(), kbd)
+        let (kbd, intercepted) = if let Some(seat) =
+            self.server_state
+                .seats
+                .iter_mut()
+                .find(|SeatPair { client, .. }| {
+                    client.kbd.as_ref().map(|k| k == keyboard).unwrap_or(false)
+                }) {
+            seat.client
+                .pressed_keys
+                .retain(|code| *code != event.raw_code);
+            let intercepted = seat.client.intercepted_keys.contains(&event.raw_code);
+            seat.client
+                .intercepted_keys
+                .retain(|code| *code != event.raw_code);
+            match seat.server.seat.get_keyboard() {
+                Some(kbd) => (kbd, intercepted),
+                None => return,
+            }
         } else {
             return;
         };
 
-        self.client_state
-            .last_key_pressed
-            .retain(|(seat_name, raw_code, _s)| seat_name != &name && raw_code.0 == event.raw_code);
-
         kbd.input::<(), _>(
             self,
-            event.raw_code,
+            keycode(event.raw_code),
             KeyState::Released,
             SERIAL_COUNTER.next_serial(),
             event.time,
-            move |_, _modifiers, _keysym| FilterResult::Forward,
+            move |_, _modifiers, _keysym| {
+                if intercepted {
+                    FilterResult::Intercept(())
+                } else {
+                    FilterResult::Forward
+                }
+            },
         );
     }
 
@@ -284,4 +298,76 @@ impl<W: WrapperSpace> KeyboardHandler for GlobalState<W> {
     }
 }
 
+impl<W: WrapperSpace> GlobalState<W> {
+    /// deliver releases for every key which is held on a seat to the embedded keyboard
+    pub(crate) fn release_pressed_keys(&mut self, seat_name: &str) {
+        let Some(seat) = self
+            .server_state
+            .seats
+            .iter_mut()
+            .find(|s| s.name == seat_name)
+        else {
+            return;
+        };
+        let pressed_keys = std::mem::take(&mut seat.client.pressed_keys);
+        let intercepted_keys = std::mem::take(&mut seat.client.intercepted_keys);
+        let time = seat.client.last_key_press.1;
+        let Some(kbd) = seat.server.seat.get_keyboard() else {
+            return;
+        };
+        let keys = pressed_keys
+            .into_iter()
+            .map(|raw_code| (raw_code, false))
+            .chain(
+                intercepted_keys
+                    .into_iter()
+                    .map(|raw_code| (raw_code, true)),
+            );
+        for (raw_code, intercepted) in keys {
+            kbd.input::<(), _>(
+                self,
+                keycode(raw_code),
+                KeyState::Released,
+                SERIAL_COUNTER.next_serial(),
+                time,
+                move |_, _modifiers, _keysym| {
+                    if intercepted {
+                        FilterResult::Intercept(())
+                    } else {
+                        FilterResult::Forward
+                    }
+                },
+            );
+        }
+    }
+
+    /// a host surface of the wrapper was destroyed
+    /// the host doesn't send a keyboard leave for it, so seats which had keyboard focus on it
+    /// release their held keys and lose the embedded focus
+    pub(crate) fn host_surface_destroyed(&mut self, surface: &WlSurface) {
+        self.drop_shortcuts_inhibitors(surface);
+        let seats: Vec<_> = {
+            let mut c_focused_surface = self.client_state.focused_surface.borrow_mut();
+            c_focused_surface
+                .iter_mut()
+                .filter(|f| &f.0 == surface && matches!(f.2, FocusStatus::Focused))
+                .map(|f| {
+                    f.2 = FocusStatus::LastFocused(Instant::now());
+                    f.1.clone()
+                })
+                .collect()
+        };
+        for seat_name in seats {
+            self.release_pressed_keys(&seat_name);
+            self.set_keyboard_focus(&seat_name, None, FocusReason::Leave);
+        }
+    }
+}
+
 delegate_keyboard!(@<W: WrapperSpace + 'static> GlobalState<W>);
+
+/// the xkb keycode of a key of the host keyboard
+fn keycode(raw_code: u32) -> Keycode {
+    // xkb keycodes are offset by 8 from the evdev keycodes sent by the host
+    Keycode::new(raw_code + 8)
+}
@@ -23,7 +23,10 @@ impl<W: WrapperSpace> LayerShellHandler for GlobalState<W> {
             .iter()
             .position(|(_, _, _, s, _, _, ..)| s.wl_surface() == layer.wl_surface())
         {
-            self.client_state.proxied_layer_surfaces.remove(i);
+            let (_, _, s_layer, ..) = self.client_state.proxied_layer_surfaces.remove(i);
+            // let the embedded client know that its layer surface won't be displayed anymore
+            s_layer.layer_surface().send_close();
+            self.host_surface_destroyed(layer.wl_surface());
         } else {
             self.space.close_layer(layer);
         }
@@ -11,8 +11,12 @@ use sctk::{
 
 use crate::{shared_state::GlobalState, space::WrapperSpace};
 
+/// xdg activation tokens of the host
+pub mod activation;
 pub mod compositor;
 pub mod data_device;
+/// dmabuf feedback from the host
+pub mod dmabuf;
 pub mod keyboard;
 pub mod layer_shell;
 /// output helpers
@@ -23,6 +27,9 @@ pub mod shell;
 pub mod toplevel;
 pub mod workspace;
 pub mod wp_fractional_scaling;
+pub mod wp_idle_inhibit;
+pub mod wp_keyboard_shortcuts_inhibit;
+pub mod wp_presentation;
 pub mod wp_security_context;
 pub mod wp_viewporter;
 
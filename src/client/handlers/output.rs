@@ -1,25 +1,35 @@
 // SPDX-License-Identifier: MPL-2.0
 
+use std::time::Duration;
+
 use sctk::{
     output::{Mode as c_Mode, OutputHandler, OutputInfo, OutputState},
     reexports::{
-        client::protocol::wl_output::Subpixel as c_Subpixel,
+        client::protocol::wl_output::{Subpixel as c_Subpixel, Transform as c_Transform},
         client::{protocol::wl_output, Connection, QueueHandle},
     },
+    shell::WaylandSurface,
 };
 use smithay::{
+    desktop::{utils::output_update, PopupManager},
     output::{Mode as s_Mode, Output, PhysicalProperties, Scale, Subpixel as s_Subpixel},
-    reexports::wayland_server::{backend::GlobalId, DisplayHandle},
-    utils::Transform,
+    reexports::{
+        calloop::timer::{TimeoutAction, Timer},
+        wayland_server::{backend::GlobalId, DisplayHandle},
+    },
+    utils::{Physical, Size, Transform},
 };
 use tracing::{error, info, warn};
-use xdg_shell_wrapper_config::WrapperConfig;
+use xdg_shell_wrapper_config::{OutputIdentity, WrapperConfig};
 
 use crate::{
     client_state::ClientState, server_state::ServerState, shared_state::GlobalState,
     space::WrapperSpace,
 };
 
+/// delay between disabling and destroying the global of an embedded output
+const OUTPUT_GLOBAL_REMOVAL_DELAY: Duration = Duration::from_secs(5);
+
 impl<W: WrapperSpace> OutputHandler for GlobalState<W> {
     fn output_state(&mut self) -> &mut OutputState {
         &mut self.client_state.output_state
@@ -35,50 +45,8 @@ impl<W: WrapperSpace> OutputHandler for GlobalState<W> {
             Some(info) if info.name.is_some() => info,
             _ => return,
         };
-
-        let GlobalState {
-            client_state:
-                ClientState {
-                    compositor_state,
-                    layer_state,
-                    viewporter_state,
-                    fractional_scaling_manager,
-                    ..
-                },
-            server_state: ServerState { display_handle, .. },
-            space,
-            ..
-        } = self;
-
-        let config = space.config();
-        let configured_outputs = match config.outputs() {
-            xdg_shell_wrapper_config::WrapperOutput::All => info.name.iter().cloned().collect(),
-            xdg_shell_wrapper_config::WrapperOutput::Name(list) => list,
-        };
-
-        if configured_outputs
-            .iter()
-            .any(|configured| Some(configured) == info.name.as_ref())
-        {
-            // construct a surface for an output if possible
-            let s_output = c_output_as_s_output::<W>(display_handle, &info);
-
-            self.client_state
-                .outputs
-                .push((output.clone(), s_output.0.clone(), s_output.1));
-            if let Err(err) = space.new_output(
-                compositor_state,
-                fractional_scaling_manager.as_ref(),
-                viewporter_state.as_ref(),
-                layer_state,
-                conn,
-                qh,
-                Some(output),
-                Some(s_output.0),
-                Some(info),
-            ) {
-                warn!("{}", err);
-            }
+        if self.output_is_configured(&output, &info, None) {
+            self.add_output(conn, qh, output, info);
         }
     }
 
@@ -92,6 +60,10 @@ impl<W: WrapperSpace> OutputHandler for GlobalState<W> {
             Some(info) if info.name.is_some() => info,
             _ => return,
         };
+        // the new info may change whether the output is configured
+        let tracked = self.client_state.outputs.iter().any(|o| o.0 == output);
+        self.configure_outputs(conn, qh, None);
+        let configured = self.client_state.outputs.iter().any(|o| o.0 == output);
 
         let GlobalState {
             client_state:
@@ -102,29 +74,19 @@ impl<W: WrapperSpace> OutputHandler for GlobalState<W> {
                     viewporter_state,
                     ..
                 },
-            server_state: ServerState { display_handle, .. },
             space,
             ..
         } = self;
 
-        let config = space.config();
-        let configured_outputs = match config.outputs() {
-            xdg_shell_wrapper_config::WrapperOutput::All => info.name.iter().cloned().collect(),
-            xdg_shell_wrapper_config::WrapperOutput::Name(list) => list,
-        };
-
-        if configured_outputs
-            .iter()
-            .any(|configured| Some(configured) == info.name.as_ref())
-        {
+        // newly configured outputs were added with the new info already
+        if tracked && configured {
             if let Some(saved_output) = self.client_state.outputs.iter_mut().find(|o| o.0 == output)
             {
+                update_s_output(&saved_output.1, &info);
                 let res = space.update_output(output.clone(), saved_output.1.clone(), info.clone());
                 if let Err(err) = res {
                     error!("{}", err);
                 } else if matches!(res, Ok(false)) {
-                    let s_output = c_output_as_s_output::<W>(display_handle, &info);
-
                     if let Err(err) = space.new_output(
                         compositor_state,
                         fractional_scaling_manager.as_ref(),
@@ -133,7 +95,7 @@ impl<W: WrapperSpace> OutputHandler for GlobalState<W> {
                         conn,
                         qh,
                         Some(output),
-                        Some(s_output.0),
+                        Some(saved_output.1.clone()),
                         Some(info),
                     ) {
                         warn!("{}", err);
@@ -145,38 +107,221 @@ impl<W: WrapperSpace> OutputHandler for GlobalState<W> {
 
     fn output_destroyed(
         &mut self,
-        _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
         output: wl_output::WlOutput,
     ) {
         info!("output destroyed {:?}", &output);
-        let info = match self.output_state().info(&output) {
-            Some(info) if info.name.is_some() => info,
-            _ => return,
-        };
-        let GlobalState { space, .. } = self;
+        // only tracked outputs were configured when they were added
+        self.remove_output(&output);
+        // another output may have become the primary output
+        // sctk removes the output from the output state only after this returns
+        self.configure_outputs(conn, qh, Some(&output));
+    }
+}
 
-        let config = space.config();
-        let configured_outputs = match config.outputs() {
-            xdg_shell_wrapper_config::WrapperOutput::All => info.name.iter().cloned().collect(),
-            xdg_shell_wrapper_config::WrapperOutput::Name(list) => list,
-        };
-        info!("output destroyed {:?}", info.name.as_ref());
+impl<W: WrapperSpace> GlobalState<W> {
+    /// apply a new config to the space at runtime
+    /// outputs which are no longer configured are removed from the space along with their
+    /// embedded outputs, and newly configured outputs are added
+    /// this has no effect unless the space implements [`WrapperSpace::set_config`], as the
+    /// outputs and key bindings are read from [`WrapperSpace::config`]
+    pub fn apply_config(&mut self, config: W::Config) {
+        let (outputs, keybindings) = (config.outputs(), config.keybindings());
+        self.space.set_config(config);
+        let applied = self.space.config();
+        if applied.outputs() != outputs || applied.keybindings() != keybindings {
+            warn!("The space didn't apply the new config, WrapperSpace::set_config may not be implemented");
+        }
+        self.reload_keybindings();
 
-        info!("configured outputs {:?}", &configured_outputs);
+        let conn = self.client_state.connection.clone();
+        let qh = self.client_state.queue_handle.clone();
+        self.configure_outputs(&conn, &qh, None);
+    }
+
+    /// match every host output against the config of the space again
+    /// outputs which are no longer configured are removed and newly configured outputs are added
+    /// a destroyed output which is still listed by the output state is skipped
+    fn configure_outputs(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        destroyed: Option<&wl_output::WlOutput>,
+    ) {
+        for output in self.client_state.output_state.outputs().collect::<Vec<_>>() {
+            if Some(&output) == destroyed {
+                continue;
+            }
+            let Some(info) = self.client_state.output_state.info(&output) else {
+                continue;
+            };
+            let configured = self.output_is_configured(&output, &info, destroyed);
+            let tracked = self.client_state.outputs.iter().any(|o| o.0 == output);
+            if configured && !tracked {
+                self.add_output(conn, qh, output, info);
+            } else if !configured && tracked {
+                self.remove_output(&output);
+            }
+        }
+    }
 
-        if configured_outputs
+    /// add a configured host output to the space, creating an embedded output for it
+    fn add_output(
+        &mut self,
+        conn: &Connection,
+        qh: &QueueHandle<Self>,
+        output: wl_output::WlOutput,
+        info: OutputInfo,
+    ) {
+        let GlobalState {
+            client_state:
+                ClientState {
+                    compositor_state,
+                    layer_state,
+                    viewporter_state,
+                    fractional_scaling_manager,
+                    ..
+                },
+            server_state: ServerState { display_handle, .. },
+            space,
+            ..
+        } = self;
+
+        // construct a surface for an output if possible
+        let s_output = c_output_as_s_output::<W>(display_handle, &info);
+
+        self.client_state
+            .outputs
+            .push((output.clone(), s_output.0.clone(), s_output.1));
+        if let Err(err) = space.new_output(
+            compositor_state,
+            fractional_scaling_manager.as_ref(),
+            viewporter_state.as_ref(),
+            layer_state,
+            conn,
+            qh,
+            Some(output),
+            Some(s_output.0),
+            Some(info),
+        ) {
+            warn!("{}", err);
+        }
+    }
+
+    /// remove a tracked host output from the space, and destroy its embedded output
+    pub(crate) fn remove_output(&mut self, output: &wl_output::WlOutput) {
+        let Some(i) = self
+            .client_state
+            .outputs
             .iter()
-            .any(|configured| Some(configured) == info.name.as_ref())
-        {
-            if let Some(saved_output) = self.client_state.outputs.iter().position(|o| o.0 == output)
-            {
-                let (c, s, _) = self.client_state.outputs.remove(saved_output);
-                if let Err(err) = space.output_leave(c, s) {
-                    warn!("{}", err);
-                }
+            .position(|o| &o.0 == output)
+        else {
+            return;
+        };
+        let (c, s, global) = self.client_state.outputs.remove(i);
+
+        // layer surfaces without an output are placed by the host, which closes them itself
+        let on_output: Vec<_> = self
+            .client_state
+            .proxied_layer_surfaces
+            .iter()
+            .filter(|(.., created_for)| created_for.as_ref() == Some(&c))
+            .map(|(_, _, s_layer, ..)| s_layer.wl_surface().clone())
+            .collect();
+
+        // embedded surfaces leave the output before its global is destroyed
+        for (_, _, s_layer, ..) in &self.client_state.proxied_layer_surfaces {
+            output_update(&s, None, s_layer.wl_surface());
+            for (popup, _) in PopupManager::popups_for_surface(s_layer.wl_surface()) {
+                output_update(&s, None, popup.wl_surface());
             }
         }
+        for seat in &self.server_state.seats {
+            if let Some(dnd_icon) = seat.server.dnd_icon.as_ref() {
+                output_update(&s, None, dnd_icon);
+            }
+        }
+
+        // the host closes layer surfaces on an output which is removed, so the embedded layer
+        // surfaces are closed as well
+        let mut closed = Vec::new();
+        self.client_state
+            .proxied_layer_surfaces
+            .retain(|(_, _, s_layer, c_layer, ..)| {
+                if on_output.contains(s_layer.wl_surface()) {
+                    s_layer.layer_surface().send_close();
+                    closed.push(c_layer.wl_surface().clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        for c_surface in closed {
+            self.host_surface_destroyed(&c_surface);
+        }
+        self.client_state
+            .pending_layer_surfaces
+            .retain(|(s_layer, requested_output, _)| {
+                if requested_output.as_ref().is_some_and(|o| s.owns(o)) {
+                    s_layer.send_close();
+                    false
+                } else {
+                    true
+                }
+            });
+
+        // the space is responsible for its own windows and popups
+        if let Err(err) = self.space.output_leave(c, s) {
+            warn!("{}", err);
+        }
+
+        // disable the global first, so that clients have time to notice it is gone before it is
+        // destroyed, avoiding protocol errors from binding a removed global
+        let dh = &self.server_state.display_handle;
+        dh.disable_global::<GlobalState<W>>(global.clone());
+        if let Err(err) = self.loop_handle.insert_source(
+            Timer::from_duration(OUTPUT_GLOBAL_REMOVAL_DELAY),
+            move |_, _, state| {
+                state
+                    .server_state
+                    .display_handle
+                    .remove_global::<GlobalState<W>>(global.clone());
+                TimeoutAction::Drop
+            },
+        ) {
+            error!("Failed to schedule removal of output global: {}", err);
+        }
+    }
+
+    /// whether a host output is selected by the outputs of the space's config
+    /// a destroyed output which is still listed by the output state is never the primary output
+    pub(crate) fn output_is_configured(
+        &self,
+        output: &wl_output::WlOutput,
+        info: &OutputInfo,
+        destroyed: Option<&wl_output::WlOutput>,
+    ) -> bool {
+        let Some(name) = info.name.as_deref() else {
+            return false;
+        };
+        let identity = OutputIdentity {
+            name,
+            make: &info.make,
+            model: &info.model,
+            // wl_output doesn't advertise serial numbers
+            serial: None,
+            description: info.description.as_deref().unwrap_or_default(),
+        };
+        // the host doesn't have a notion of a primary output, so the first advertised output is used
+        let is_primary = self
+            .client_state
+            .output_state
+            .outputs()
+            .find(|o| Some(o) != destroyed)
+            .as_ref()
+            == Some(output);
+        self.space.config().outputs().matches(&identity, is_primary)
     }
 }
 
@@ -201,30 +346,85 @@ pub fn c_output_as_s_output<W: WrapperSpace + 'static>(
             model: info.model.clone(),       // model of the monitor
         },
     );
-    for c_Mode {
-        dimensions,
-        refresh_rate,
-        current,
-        preferred,
-    } in &info.modes
-    {
-        let s_mode = s_Mode {
-            size: (*dimensions).into(),
-            refresh: *refresh_rate,
-        };
-        if *preferred {
+    update_s_output(&s_output, info);
+    let s_output_global = s_output.create_global::<GlobalState<W>>(dh);
+    (s_output, s_output_global)
+}
+
+/// update the state of a server output to match the info of its client output
+/// the xdg-output logical position and size are derived by smithay from this state
+pub fn update_s_output(s_output: &Output, info: &OutputInfo) {
+    let modes: Vec<s_Mode> = info
+        .modes
+        .iter()
+        .map(
+            |c_Mode {
+                 dimensions,
+                 refresh_rate,
+                 ..
+             }| s_Mode {
+                size: (*dimensions).into(),
+                refresh: *refresh_rate,
+            },
+        )
+        .collect();
+    for old_mode in s_output.modes() {
+        if !modes.contains(&old_mode) {
+            s_output.delete_mode(old_mode);
+        }
+    }
+    for (c_mode, s_mode) in info.modes.iter().zip(modes) {
+        s_output.add_mode(s_mode);
+        if c_mode.preferred {
             s_output.set_preferred(s_mode);
         }
-        if *current {
-            s_output.change_current_state(
-                Some(s_mode),
-                Some(Transform::Normal),
-                Some(Scale::Integer(info.scale_factor)),
-                Some(info.location.into()),
-            )
+    }
+
+    let current_mode = info.modes.iter().find(|m| m.current);
+    let transform = c_transform_as_s_transform(info.transform);
+    s_output.change_current_state(
+        current_mode.map(|m| s_Mode {
+            size: m.dimensions.into(),
+            refresh: m.refresh_rate,
+        }),
+        Some(transform),
+        Some(output_scale(info, current_mode, transform)),
+        Some(info.logical_position.unwrap_or(info.location).into()),
+    );
+}
+
+/// the scale of an output
+/// hosts only advertise integer scales on the output, so a fractional scale is derived from the
+/// logical size when it differs from the size of the transformed current mode
+fn output_scale(info: &OutputInfo, current_mode: Option<&c_Mode>, transform: Transform) -> Scale {
+    let fractional = current_mode
+        .zip(info.logical_size)
+        .and_then(|(mode, logical_size)| {
+            let mode_size = transform.transform_size(Size::<i32, Physical>::from(mode.dimensions));
+            (logical_size.0 > 0 && mode_size.w > 0)
+                .then(|| mode_size.w as f64 / logical_size.0 as f64)
+        });
+    match fractional {
+        Some(fractional) if (fractional - info.scale_factor as f64).abs() > f64::EPSILON => {
+            Scale::Custom {
+                advertised_integer: info.scale_factor,
+                fractional,
+            }
         }
-        s_output.add_mode(s_mode);
+        _ => Scale::Integer(info.scale_factor),
+    }
+}
+
+/// convert a client output transform to a server transform
+fn c_transform_as_s_transform(transform: c_Transform) -> Transform {
+    match transform {
+        c_Transform::_90 => Transform::_90,
+        c_Transform::_180 => Transform::_180,
+        c_Transform::_270 => Transform::_270,
+        c_Transform::Flipped => Transform::Flipped,
+        c_Transform::Flipped90 => Transform::Flipped90,
+        c_Transform::Flipped180 => Transform::Flipped180,
+        c_Transform::Flipped270 => Transform::Flipped270,
+        _ => Transform::Normal,
     }
-    let s_output_global = s_output.create_global::<GlobalState<W>>(dh);
-    (s_output, s_output_global)
 }
@@ -83,11 +83,6 @@ impl<W: WrapperSpace> GlobalState<W> {
             .seat
             .get_pointer()
             .unwrap();
-        let kbd = self.server_state.seats[seat_index]
-            .server
-            .seat
-            .get_keyboard()
-            .unwrap();
         for e in events {
             let seat = &mut self.server_state.seats[seat_index];
             match e.kind {
@@ -154,6 +149,7 @@ impl<W: WrapperSpace> GlobalState<W> {
                             }
                         });
                     if let Some(s_surface) = s_surface {
+                        self.focus_hovered(&seat_name, &s_surface);
                         ptr.motion(
                             self,
                             Some((s_surface, Point::default())),
@@ -178,9 +174,10 @@ impl<W: WrapperSpace> GlobalState<W> {
                         &seat_name,
                         e.surface.clone(),
                     ) {
+                        self.focus_hovered(&seat_name, &surface);
                         ptr.motion(
                             self,
-                            Some((surface.clone(), s_pos)),
+                            Some((surface.clone(), s_pos.to_f64())),
                             &MotionEvent {
                                 location: c_pos.to_f64() + Point::from((surface_x, surface_y)),
                                 serial: SERIAL_COUNTER.next_serial(),
@@ -228,6 +225,7 @@ impl<W: WrapperSpace> GlobalState<W> {
                             }
                         });
                     if let Some(s_surface) = s_surface {
+                        self.focus_hovered(&seat_name, &s_surface);
                         ptr.motion(
                             self,
                             Some((s_surface, Point::default())),
@@ -251,9 +249,10 @@ impl<W: WrapperSpace> GlobalState<W> {
                         &seat_name,
                         c_focused_surface,
                     ) {
+                        self.focus_hovered(&seat_name, &surface);
                         ptr.motion(
                             self,
-                            Some((surface.clone(), s_pos)),
+                            Some((surface.clone(), s_pos.to_f64())),
                             &MotionEvent {
                                 location: c_pos.to_f64() + Point::from((surface_x, surface_y)),
                                 serial: SERIAL_COUNTER.next_serial(),
@@ -301,7 +300,7 @@ impl<W: WrapperSpace> GlobalState<W> {
                             }
                         });
                     if let Some(s_surface) = s_surface {
-                        kbd.set_focus(self, Some(s_surface), SERIAL_COUNTER.next_serial());
+                        self.focus_clicked(&seat_name, Some(s_surface));
 
                         ptr.button(
                             self,
@@ -318,8 +317,7 @@ impl<W: WrapperSpace> GlobalState<W> {
                     }
 
                     let s = self.space.handle_button(&seat_name, true);
-
-                    kbd.set_focus(self, s, SERIAL_COUNTER.next_serial());
+                    self.focus_clicked(&seat_name, s);
                     ptr.button(
                         self,
                         &ButtonEvent {
@@ -346,9 +344,8 @@ impl<W: WrapperSpace> GlobalState<W> {
                                 None
                             }
                         });
-                    if let Some(s_surface) = s_surface {
-                        kbd.set_focus(self, Some(s_surface), SERIAL_COUNTER.next_serial());
-
+                    if s_surface.is_some() {
+                        // focus only changes when a button is pressed
                         ptr.button(
                             self,
                             &ButtonEvent {
@@ -363,8 +360,8 @@ impl<W: WrapperSpace> GlobalState<W> {
                         continue;
                     }
 
-                    let s = self.space.handle_button(&seat_name, false);
-                    kbd.set_focus(self, s, SERIAL_COUNTER.next_serial());
+                    // focus only changes when a button is pressed
+                    let _ = self.space.handle_button(&seat_name, false);
 
                     ptr.button(
                         self,
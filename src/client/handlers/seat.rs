@@ -75,7 +75,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
             self.server_state.seats.push(SeatPair {
                 name,
                 client: ClientSeat {
-                    _seat: seat.clone(),
+                    seat: seat.clone(),
                     kbd,
                     ptr,
                     data_device,
@@ -83,6 +83,8 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
                     dnd_source: None,
                     last_enter: 0,
                     last_key_press: (0, 0),
+                    pressed_keys: Vec::new(),
+                    intercepted_keys: Vec::new(),
                     last_pointer_press: (0, 0),
                     selection_offer: None,
                     dnd_offer: None,
@@ -96,6 +98,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
                     selection_source: None,
                     dnd_source: None,
                     dnd_icon: None,
+                    dnd_client: None,
                 },
             });
         }
@@ -117,7 +120,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
             .server_state
             .seats
             .iter_mut()
-            .find(|sp| sp.client._seat == seat)
+            .find(|sp| sp.client.seat == seat)
         {
             sp
         } else {
@@ -129,7 +132,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
             self.server_state.seats.push(SeatPair {
                 name,
                 client: ClientSeat {
-                    _seat: seat.clone(),
+                    seat: seat.clone(),
                     kbd: None,
                     ptr: None,
                     data_device: self
@@ -142,6 +145,8 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
                     dnd_offer: None,
                     last_enter: 0,
                     last_key_press: (0, 0),
+                    pressed_keys: Vec::new(),
+                    intercepted_keys: Vec::new(),
                     last_pointer_press: (0, 0),
                     next_selection_offer_is_mine: false,
                     next_dnd_offer_is_mine: false,
@@ -152,6 +157,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
                     selection_source: None,
                     dnd_source: None,
                     dnd_icon: None,
+                    dnd_client: None,
                 },
             });
             self.server_state.seats.last_mut().unwrap()
@@ -199,7 +205,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
             .server_state
             .seats
             .iter_mut()
-            .find(|sp| sp.client._seat == seat)
+            .find(|sp| sp.client.seat == seat)
         {
             sp
         } else {
@@ -222,7 +228,7 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
             .server_state
             .seats
             .iter()
-            .position(|sp| sp.client._seat == seat)
+            .position(|sp| sp.client.seat == seat)
         {
             self.server_state.seats.swap_remove(sp_i)
         } else {
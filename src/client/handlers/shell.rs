@@ -1,9 +1,51 @@
 use sctk::{
     delegate_xdg_popup, delegate_xdg_shell, delegate_xdg_window,
-    shell::xdg::{popup::PopupHandler, window::WindowHandler},
+    shell::xdg::{
+        popup::PopupHandler,
+        window::{Window, WindowConfigure, WindowHandler, WindowManagerCapabilities, WindowState},
+    },
 };
+use smithay::{
+    desktop::Window as SmithayWindow,
+    reexports::wayland_protocols::xdg::shell::server::xdg_toplevel,
+};
+
+use crate::{
+    server::handlers::xdg_shell::apply_size_constraints, shared_state::GlobalState,
+    space::WrapperSpace,
+};
+
+/// host window states which are relayed to embedded toplevels
+const RELAYED_STATES: [(WindowState, xdg_toplevel::State); 8] = [
+    (WindowState::MAXIMIZED, xdg_toplevel::State::Maximized),
+    (WindowState::FULLSCREEN, xdg_toplevel::State::Fullscreen),
+    (WindowState::RESIZING, xdg_toplevel::State::Resizing),
+    (WindowState::ACTIVATED, xdg_toplevel::State::Activated),
+    (WindowState::TILED_LEFT, xdg_toplevel::State::TiledLeft),
+    (WindowState::TILED_RIGHT, xdg_toplevel::State::TiledRight),
+    (WindowState::TILED_TOP, xdg_toplevel::State::TiledTop),
+    (WindowState::TILED_BOTTOM, xdg_toplevel::State::TiledBottom),
+];
 
-use crate::{shared_state::GlobalState, space::WrapperSpace};
+/// host window manager capabilities which are relayed to embedded toplevels
+const RELAYED_CAPABILITIES: [(WindowManagerCapabilities, xdg_toplevel::WmCapabilities); 4] = [
+    (
+        WindowManagerCapabilities::WINDOW_MENU,
+        xdg_toplevel::WmCapabilities::WindowMenu,
+    ),
+    (
+        WindowManagerCapabilities::MAXIMIZE,
+        xdg_toplevel::WmCapabilities::Maximize,
+    ),
+    (
+        WindowManagerCapabilities::FULLSCREEN,
+        xdg_toplevel::WmCapabilities::Fullscreen,
+    ),
+    (
+        WindowManagerCapabilities::MINIMIZE,
+        xdg_toplevel::WmCapabilities::Minimize,
+    ),
+];
 
 impl<W: WrapperSpace> PopupHandler for GlobalState<W> {
     fn configure(
@@ -31,20 +73,73 @@ impl<W: WrapperSpace> WindowHandler for GlobalState<W> {
         &mut self,
         _conn: &sctk::reexports::client::Connection,
         _qh: &sctk::reexports::client::QueueHandle<Self>,
-        _window: &sctk::shell::xdg::window::Window,
+        window: &Window,
     ) {
-        // nothing to be done
+        if let Some(toplevel) = self
+            .embedded_window(window)
+            .and_then(|w| w.toplevel().cloned())
+        {
+            toplevel.send_close();
+        }
     }
 
     fn configure(
         &mut self,
         _conn: &sctk::reexports::client::Connection,
         _qh: &sctk::reexports::client::QueueHandle<Self>,
-        _window: &sctk::shell::xdg::window::Window,
-        _configure: sctk::shell::xdg::window::WindowConfigure,
+        window: &Window,
+        configure: WindowConfigure,
         _serial: u32,
     ) {
-        // nothing to be done
+        let Some(toplevel) = self
+            .embedded_window(window)
+            .and_then(|w| w.toplevel().cloned())
+        else {
+            return;
+        };
+        toplevel.with_pending_state(|state| {
+            state.size = match configure.new_size {
+                (None, None) => None,
+                // the embedded toplevel chooses the dimensions which the host leaves to it
+                (w, h) => Some(
+                    (
+                        w.map_or(0, |w| w.get() as i32),
+                        h.map_or(0, |h| h.get() as i32),
+                    )
+                        .into(),
+                ),
+            };
+            state.bounds = configure
+                .suggested_bounds
+                .map(|(w, h)| (w as i32, h as i32).into());
+            for (host_state, s_state) in RELAYED_STATES {
+                if configure.state.contains(host_state) {
+                    state.states.set(s_state);
+                } else {
+                    state.states.unset(s_state);
+                }
+            }
+            for (host_capability, s_capability) in RELAYED_CAPABILITIES {
+                if configure.capabilities.contains(host_capability) {
+                    state.capabilities.set(s_capability);
+                } else {
+                    state.capabilities.unset(s_capability);
+                }
+            }
+        });
+        apply_size_constraints(&toplevel);
+        toplevel.send_configure();
+    }
+}
+
+impl<W: WrapperSpace> GlobalState<W> {
+    /// the embedded window which is displayed by a host window of the space
+    fn embedded_window(&self, window: &Window) -> Option<SmithayWindow> {
+        self.space
+            .proxied_windows()
+            .into_iter()
+            .find(|(c_window, _)| c_window == window)
+            .map(|(_, s_window)| s_window)
     }
 }
 
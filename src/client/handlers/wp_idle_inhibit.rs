@@ -0,0 +1,80 @@
+//! Handling of the idle inhibitors of the host.
+
+use std::marker::PhantomData;
+
+use sctk::reexports::client::globals::{BindError, GlobalList};
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
+use sctk::reexports::client::Dispatch;
+use sctk::reexports::client::{delegate_dispatch, Connection, Proxy, QueueHandle};
+use sctk::reexports::protocols::wp::idle_inhibit::zv1::client::{
+    zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
+};
+
+use sctk::globals::GlobalData;
+
+use crate::shared_state::GlobalState;
+use crate::space::WrapperSpace;
+
+/// Idle inhibit manager.
+#[derive(Debug, Clone)]
+pub struct IdleInhibitManager<T> {
+    manager: ZwpIdleInhibitManagerV1,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: 'static + WrapperSpace> IdleInhibitManager<T> {
+    /// Create new idle inhibit manager.
+    pub fn new(
+        globals: &GlobalList,
+        queue_handle: &QueueHandle<GlobalState<T>>,
+    ) -> Result<Self, BindError> {
+        let manager = globals.bind(queue_handle, 1..=1, GlobalData)?;
+        Ok(Self {
+            manager,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Inhibit idling of the host while the given host surface is visible.
+    pub fn create_inhibitor(
+        &self,
+        surface: &WlSurface,
+        queue_handle: &QueueHandle<GlobalState<T>>,
+    ) -> ZwpIdleInhibitorV1 {
+        self.manager
+            .create_inhibitor(surface, queue_handle, GlobalData)
+    }
+}
+
+impl<T: 'static + WrapperSpace> Dispatch<ZwpIdleInhibitManagerV1, GlobalData, GlobalState<T>>
+    for IdleInhibitManager<T>
+{
+    fn event(
+        _: &mut GlobalState<T>,
+        _: &ZwpIdleInhibitManagerV1,
+        _: <ZwpIdleInhibitManagerV1 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<GlobalState<T>>,
+    ) {
+        // No events.
+    }
+}
+
+impl<T: 'static + WrapperSpace> Dispatch<ZwpIdleInhibitorV1, GlobalData, GlobalState<T>>
+    for IdleInhibitManager<T>
+{
+    fn event(
+        _: &mut GlobalState<T>,
+        _: &ZwpIdleInhibitorV1,
+        _: <ZwpIdleInhibitorV1 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<GlobalState<T>>,
+    ) {
+        // No events.
+    }
+}
+
+delegate_dispatch!(@<T: 'static + WrapperSpace> GlobalState<T>: [ZwpIdleInhibitManagerV1: GlobalData] => IdleInhibitManager<T>);
+delegate_dispatch!(@<T: 'static + WrapperSpace> GlobalState<T>: [ZwpIdleInhibitorV1: GlobalData] => IdleInhibitManager<T>);
@@ -0,0 +1,91 @@
+//! Handling of the keyboard shortcuts inhibitors of the host.
+
+use std::marker::PhantomData;
+
+use sctk::reexports::client::globals::{BindError, GlobalList};
+use sctk::reexports::client::protocol::{wl_seat::WlSeat, wl_surface::WlSurface};
+use sctk::reexports::client::Dispatch;
+use sctk::reexports::client::{delegate_dispatch, Connection, Proxy, QueueHandle};
+use sctk::reexports::protocols::wp::keyboard_shortcuts_inhibit::zv1::client::{
+    zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1,
+    zwp_keyboard_shortcuts_inhibitor_v1::{self, ZwpKeyboardShortcutsInhibitorV1},
+};
+
+use sctk::globals::GlobalData;
+use smithay::wayland::keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitor;
+
+use crate::shared_state::GlobalState;
+use crate::space::WrapperSpace;
+
+/// Keyboard shortcuts inhibit manager.
+#[derive(Debug, Clone)]
+pub struct KeyboardShortcutsInhibitManager<T> {
+    manager: ZwpKeyboardShortcutsInhibitManagerV1,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: 'static + WrapperSpace> KeyboardShortcutsInhibitManager<T> {
+    /// Create new keyboard shortcuts inhibit manager.
+    pub fn new(
+        globals: &GlobalList,
+        queue_handle: &QueueHandle<GlobalState<T>>,
+    ) -> Result<Self, BindError> {
+        let manager = globals.bind(queue_handle, 1..=1, GlobalData)?;
+        Ok(Self {
+            manager,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Inhibit the shortcuts of the host on a host surface for an embedded inhibitor.
+    /// The activity of the host inhibitor is relayed to the embedded inhibitor.
+    pub fn inhibit_shortcuts(
+        &self,
+        surface: &WlSurface,
+        seat: &WlSeat,
+        inhibitor: KeyboardShortcutsInhibitor,
+        queue_handle: &QueueHandle<GlobalState<T>>,
+    ) -> ZwpKeyboardShortcutsInhibitorV1 {
+        self.manager
+            .inhibit_shortcuts(surface, seat, queue_handle, inhibitor)
+    }
+}
+
+impl<T: 'static + WrapperSpace>
+    Dispatch<ZwpKeyboardShortcutsInhibitManagerV1, GlobalData, GlobalState<T>>
+    for KeyboardShortcutsInhibitManager<T>
+{
+    fn event(
+        _: &mut GlobalState<T>,
+        _: &ZwpKeyboardShortcutsInhibitManagerV1,
+        _: <ZwpKeyboardShortcutsInhibitManagerV1 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<GlobalState<T>>,
+    ) {
+        // No events.
+    }
+}
+
+impl<T: 'static + WrapperSpace>
+    Dispatch<ZwpKeyboardShortcutsInhibitorV1, KeyboardShortcutsInhibitor, GlobalState<T>>
+    for KeyboardShortcutsInhibitManager<T>
+{
+    fn event(
+        _: &mut GlobalState<T>,
+        _: &ZwpKeyboardShortcutsInhibitorV1,
+        event: <ZwpKeyboardShortcutsInhibitorV1 as Proxy>::Event,
+        inhibitor: &KeyboardShortcutsInhibitor,
+        _: &Connection,
+        _: &QueueHandle<GlobalState<T>>,
+    ) {
+        match event {
+            zwp_keyboard_shortcuts_inhibitor_v1::Event::Active => inhibitor.activate(),
+            zwp_keyboard_shortcuts_inhibitor_v1::Event::Inactive => inhibitor.inactivate(),
+            _ => {}
+        }
+    }
+}
+
+delegate_dispatch!(@<T: 'static + WrapperSpace> GlobalState<T>: [ZwpKeyboardShortcutsInhibitManagerV1: GlobalData] => KeyboardShortcutsInhibitManager<T>);
+delegate_dispatch!(@<T: 'static + WrapperSpace> GlobalState<T>: [ZwpKeyboardShortcutsInhibitorV1: KeyboardShortcutsInhibitor] => KeyboardShortcutsInhibitManager<T>);
@@ -0,0 +1,139 @@
+//! Handling of the wp-presentation-time.
+
+use std::{marker::PhantomData, sync::Mutex, time::Duration};
+
+use sctk::globals::GlobalData;
+use sctk::reexports::client::globals::{BindError, GlobalList};
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
+use sctk::reexports::client::{delegate_dispatch, Connection, Dispatch, Proxy, QueueHandle};
+use sctk::reexports::protocols::wp::presentation_time::client::{
+    wp_presentation::{self, WpPresentation},
+    wp_presentation_feedback::{self, WpPresentationFeedback},
+};
+use smithay::{
+    desktop::utils::OutputPresentationFeedback,
+    reexports::wayland_protocols::wp::presentation_time::server::wp_presentation_feedback::Kind,
+    utils::Monotonic,
+    wayland::presentation::{PresentationState, Refresh},
+};
+
+use crate::shared_state::GlobalState;
+use crate::space::WrapperSpace;
+
+/// Presentation time.
+#[derive(Debug, Clone)]
+pub struct PresentationTimeState<T> {
+    presentation: WpPresentation,
+    _phantom: PhantomData<T>,
+}
+
+/// Presentation feedback data.
+/// Holds the feedback of the embedded surfaces which contributed to a host frame.
+#[derive(Debug)]
+pub struct PresentationFeedbackData {
+    feedback: Mutex<Option<OutputPresentationFeedback>>,
+}
+
+impl<T: 'static + WrapperSpace> PresentationTimeState<T> {
+    /// Create new presentation time state.
+    pub fn new(
+        globals: &GlobalList,
+        queue_handle: &QueueHandle<GlobalState<T>>,
+    ) -> Result<Self, BindError> {
+        let presentation = globals.bind(queue_handle, 1..=1, GlobalData)?;
+        Ok(Self {
+            presentation,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Request presentation feedback for the next commit of a host surface.
+    /// The embedded feedback is completed when the host reports the frame as presented or discarded.
+    pub fn feedback(
+        &self,
+        surface: &WlSurface,
+        feedback: OutputPresentationFeedback,
+        queue_handle: &QueueHandle<GlobalState<T>>,
+    ) -> WpPresentationFeedback {
+        let data = PresentationFeedbackData {
+            feedback: Mutex::new(Some(feedback)),
+        };
+        self.presentation.feedback(surface, queue_handle, data)
+    }
+}
+
+impl<T: 'static + WrapperSpace> Dispatch<WpPresentation, GlobalData, GlobalState<T>>
+    for PresentationTimeState<T>
+{
+    fn event(
+        state: &mut GlobalState<T>,
+        _: &WpPresentation,
+        event: <WpPresentation as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<GlobalState<T>>,
+    ) {
+        if let wp_presentation::Event::ClockId { clk_id } = event {
+            // embedded clients must use the same clock as the host
+            if state.server_state.presentation_state.is_none() {
+                state.server_state.presentation_state =
+                    Some(PresentationState::new::<GlobalState<T>>(
+                        &state.server_state.display_handle,
+                        clk_id,
+                    ));
+            }
+        }
+    }
+}
+
+impl<T: 'static + WrapperSpace>
+    Dispatch<WpPresentationFeedback, PresentationFeedbackData, GlobalState<T>>
+    for PresentationTimeState<T>
+{
+    fn event(
+        _: &mut GlobalState<T>,
+        _: &WpPresentationFeedback,
+        event: <WpPresentationFeedback as Proxy>::Event,
+        data: &PresentationFeedbackData,
+        _: &Connection,
+        _: &QueueHandle<GlobalState<T>>,
+    ) {
+        match event {
+            wp_presentation_feedback::Event::Presented {
+                tv_sec_hi,
+                tv_sec_lo,
+                tv_nsec,
+                refresh,
+                seq_hi,
+                seq_lo,
+                flags,
+            } => {
+                let Some(mut feedback) = data.feedback.lock().unwrap().take() else {
+                    return;
+                };
+                let secs = (u64::from(tv_sec_hi) << 32) | u64::from(tv_sec_lo);
+                let time = Duration::new(secs, tv_nsec);
+                let seq = (u64::from(seq_hi) << 32) | u64::from(seq_lo);
+                let flags = flags
+                    .into_result()
+                    .map(|flags| Kind::from_bits_truncate(flags.bits()))
+                    .unwrap_or(Kind::empty());
+                // a refresh of 0 means the host doesn't know it
+                let refresh = match refresh {
+                    0 => Refresh::Unknown,
+                    refresh => Refresh::Fixed(Duration::from_nanos(refresh.into())),
+                };
+                feedback.presented::<_, Monotonic>(time, refresh, seq, flags);
+            }
+            wp_presentation_feedback::Event::Discarded => {
+                if let Some(mut feedback) = data.feedback.lock().unwrap().take() {
+                    feedback.discarded();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+delegate_dispatch!(@<T: 'static + WrapperSpace> GlobalState<T>: [WpPresentation: GlobalData] => PresentationTimeState<T>);
+delegate_dispatch!(@<T: 'static + WrapperSpace> GlobalState<T>: [WpPresentationFeedback: PresentationFeedbackData] => PresentationTimeState<T>);
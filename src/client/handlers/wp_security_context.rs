@@ -1,19 +1,16 @@
-use std::{
-    os::{
-        linux::net::SocketAddrExt,
-        unix::net::{SocketAddr, UnixListener, UnixStream},
-    },
-    sync::{Arc, Mutex},
+use std::os::{
+    fd::OwnedFd,
+    unix::net::{UnixListener, UnixStream},
 };
 
 use cctk::wayland_client::{
     delegate_dispatch,
     globals::{BindError, GlobalList},
-    Dispatch, Proxy, QueueHandle,
+    Dispatch, QueueHandle,
 };
-use rand::distributions::{Alphanumeric, DistString};
 use rustix::fd::AsFd;
 use sctk::globals::GlobalData;
+use tempfile::TempDir;
 
 use wayland_protocols::wp::security_context::v1::client::{
     wp_security_context_manager_v1::WpSecurityContextManagerV1,
@@ -22,15 +19,44 @@ use wayland_protocols::wp::security_context::v1::client::{
 
 use crate::{shared_state::GlobalState, space::WrapperSpace};
 
+/// name of the socket of a listener in its private directory
+const LISTENER_SOCKET_NAME: &str = "wayland-sandbox";
+
 #[derive(Debug, Clone)]
+/// Security context manager.
 pub struct SecurityContextManager {
+    /// the host security context manager
     pub manager: WpSecurityContextManagerV1,
 }
 
+/// metadata of a sandboxed applet, attached to its security context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SandboxInfo {
+    /// name of the sandbox engine, ex. `com.system76.CosmicPanel`
+    pub sandbox_engine: String,
+    /// app id of the sandboxed applet
+    pub app_id: String,
+    /// id of this instance of the sandboxed applet
+    pub instance_id: String,
+}
+
 /// Security Context data.
 #[derive(Debug)]
 pub struct SecurityContext {
-    pub conn: Arc<Mutex<Option<UnixStream>>>,
+    /// metadata which was committed with the security context
+    pub sandbox: SandboxInfo,
+}
+
+/// a committed security context on the host
+/// the host accepts connections on the listener of the context until this is dropped, so it must
+/// outlive the connect of the applet, which is ensured by inserting the applet with
+/// [`crate::launcher::ClientLauncher::get_sandboxed_client_sock`]
+#[derive(Debug)]
+pub struct SecurityContextListener {
+    context: WpSecurityContextV1,
+    conn: Option<UnixStream>,
+    /// the host stops listening once the other end of this pipe is closed
+    _close_fd: OwnedFd,
 }
 
 impl SecurityContextManager {
@@ -43,40 +69,65 @@ impl SecurityContextManager {
         Ok(Self { manager })
     }
 
-    /// Create a new security context.
+    /// Create a new committed security context for a sandboxed applet.
+    /// The returned listener holds a single connection to the host which is restricted by the
+    /// context, and which should be handed to the applet.
     pub fn create_listener<T: 'static + WrapperSpace>(
         &self,
+        sandbox: SandboxInfo,
         qh: &QueueHandle<GlobalState<T>>,
-    ) -> std::io::Result<WpSecurityContextV1> {
-        // create a close fd that we can use to close the listener
+    ) -> std::io::Result<SecurityContextListener> {
+        // the socket is bound in a private directory, and unlinked once we are connected, so no
+        // one else can connect to the listener
+        let dir = TempDir::new()?;
+        let path = dir.path().join(LISTENER_SOCKET_NAME);
+        let listener = UnixListener::bind(&path)?;
+        let conn = UnixStream::connect(&path)?;
+        dir.close()?;
+
+        // the host closes the listener once our end of the pipe is closed
         let (close_fd_ours, close_fd) = rustix::pipe::pipe()?;
-        let s: String = Alphanumeric.sample_string(&mut rand::thread_rng(), 50);
-        let addr = SocketAddr::from_abstract_name(s)?;
-        // this also listens on the socket
-        let listener = UnixListener::bind_addr(&addr)?;
-        let wp_security_context = self.manager.create_listener(
+        let context = self.manager.create_listener(
             listener.as_fd(),
             close_fd.as_fd(),
             qh,
             SecurityContext {
-                conn: Arc::new(Mutex::new(None)),
+                sandbox: sandbox.clone(),
             },
         );
-        let conn = UnixStream::connect_addr(&addr)?;
-        // XXX make sure no one else can connect to the listener
-        drop(close_fd_ours);
+        context.set_sandbox_engine(sandbox.sandbox_engine);
+        context.set_app_id(sandbox.app_id);
+        context.set_instance_id(sandbox.instance_id);
+        context.commit();
 
-        // we need to store the connection somewhere
-        {
-            let data = wp_security_context.data::<SecurityContext>().unwrap();
-            let mut guard = data.conn.lock().unwrap();
-            *guard = Some(conn);
-        }
+        // the listener fd is duplicated when the request is sent, so the host owns it now
+        drop(listener);
 
-        // XXX the compositor will close the listener fd
-        Box::leak(Box::new(listener));
+        Ok(SecurityContextListener {
+            context,
+            conn: Some(conn),
+            _close_fd: close_fd_ours,
+        })
+    }
+}
+
+impl SecurityContextListener {
+    /// metadata of the sandboxed applet
+    pub fn sandbox(&self) -> &SandboxInfo {
+        &self.context.data::<SecurityContext>().unwrap().sandbox
+    }
+
+    /// take the connection to the host which is restricted by the security context
+    /// the host may only accept it after this returns, so the listener must not be dropped yet
+    pub fn take_connection(&mut self) -> Option<UnixStream> {
+        self.conn.take()
+    }
+}
 
-        Ok(wp_security_context)
+impl Drop for SecurityContextListener {
+    fn drop(&mut self) {
+        // the committed context stays active for connections which were already accepted
+        self.context.destroy();
     }
 }
 
@@ -92,7 +143,6 @@ impl<T: 'static + WrapperSpace> Dispatch<WpSecurityContextManagerV1, GlobalData,
         _qhandle: &cctk::wayland_client::QueueHandle<GlobalState<T>>,
     ) {
         // No events.
-        unimplemented!()
     }
 }
 
@@ -108,7 +158,6 @@ impl<T: 'static + WrapperSpace> Dispatch<WpSecurityContextV1, SecurityContext, G
         _qhandle: &cctk::wayland_client::QueueHandle<GlobalState<T>>,
     ) {
         // No events.
-        unimplemented!()
     }
 }
 
@@ -2,17 +2,20 @@ use crate::space::{ToplevelInfoSpace, ToplevelManagerSpace, WorkspaceHandlerSpac
 use crate::{server_state::ServerState, shared_state::GlobalState, space::WrapperSpace};
 use cctk::workspace::WorkspaceState;
 use cctk::{toplevel_info::ToplevelInfoState, toplevel_management::ToplevelManagerState};
+use sctk::activation::ActivationState;
 use sctk::data_device_manager::data_device::DataDevice;
 use sctk::data_device_manager::data_offer::{DragOffer, SelectionOffer};
 use sctk::data_device_manager::data_source::{CopyPasteSource, DragSource};
 use sctk::data_device_manager::DataDeviceManagerState;
+use sctk::dmabuf::{DmabufFeedback, DmabufState};
 use sctk::reexports::calloop_wayland_source::WaylandSource;
 use sctk::seat::pointer::ThemedPointer;
 use sctk::shell::wlr_layer::LayerSurface;
+use sctk::shell::WaylandSurface;
 use sctk::shell::{wlr_layer::LayerShell, xdg::XdgShell};
 use sctk::shm::Shm;
 use sctk::{
-    compositor::CompositorState,
+    compositor::{CompositorState, SurfaceData},
     output::OutputState,
     reexports::client::{
         globals::registry_queue_init,
@@ -22,7 +25,7 @@ use sctk::{
             wl_seat::WlSeat,
             wl_surface::{self, WlSurface},
         },
-        Connection, QueueHandle,
+        Connection, Proxy, QueueHandle,
     },
     registry::RegistryState,
     seat::SeatState,
@@ -35,13 +38,22 @@ use smithay::backend::renderer::gles::GlesRenderer;
 use smithay::backend::renderer::{Bind, Unbind};
 use smithay::reexports::wayland_server::backend::{ClientData, ClientId, DisconnectReason};
 use smithay::wayland::compositor::CompositorClientState;
+use smithay::wayland::keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitor;
 use smithay::{
     backend::egl::EGLSurface,
-    desktop::LayerSurface as SmithayLayerSurface,
+    desktop::{
+        utils::{send_frames_surface_tree, OutputPresentationFeedback},
+        LayerSurface as SmithayLayerSurface,
+    },
     output::Output,
     reexports::{
         calloop,
-        wayland_server::{backend::GlobalId, protocol::wl_output},
+        wayland_protocols::wp::presentation_time::server::wp_presentation_feedback,
+        wayland_server::{
+            backend::GlobalId,
+            protocol::{wl_output, wl_surface::WlSurface as s_WlSurface},
+            Client,
+        },
     },
 };
 use std::fmt::Debug;
@@ -49,19 +61,30 @@ use std::time::Duration;
 use std::{cell::RefCell, rc::Rc, time::Instant};
 use tracing::error;
 use wayland_protocols::wp::fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1;
+use wayland_protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
+use wayland_protocols::wp::keyboard_shortcuts_inhibit::zv1::client::zwp_keyboard_shortcuts_inhibitor_v1::ZwpKeyboardShortcutsInhibitorV1;
 use wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport;
 
 use super::handlers::wp_fractional_scaling::FractionalScalingManager;
-use super::handlers::wp_security_context::SecurityContextManager;
+use super::handlers::wp_idle_inhibit::IdleInhibitManager;
+use super::handlers::wp_keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitManager;
+use super::handlers::wp_presentation::PresentationTimeState;
+use super::handlers::wp_security_context::{
+    SandboxInfo, SecurityContextListener, SecurityContextManager,
+};
 use super::handlers::wp_viewporter::ViewporterState;
 
 #[derive(Debug)]
 pub(crate) struct ClientSeat {
-    pub(crate) _seat: WlSeat,
+    pub(crate) seat: WlSeat,
     pub(crate) kbd: Option<wl_keyboard::WlKeyboard>,
     pub(crate) ptr: Option<ThemedPointer>,
     pub(crate) last_enter: u32,
     pub(crate) last_key_press: (u32, u32),
+    /// raw codes of the keys which are held on the host keyboard
+    pub(crate) pressed_keys: Vec<u32>,
+    /// raw codes of held keys which were intercepted by a key binding
+    pub(crate) intercepted_keys: Vec<u32>,
     pub(crate) last_pointer_press: (u32, u32),
     pub(crate) data_device: DataDevice,
     pub(crate) copy_paste_source: Option<CopyPasteSource>,
@@ -125,6 +148,8 @@ pub struct ClientState<W: WrapperSpace + 'static> {
     pub fractional_scaling_manager: Option<FractionalScalingManager<W>>,
     /// viewporter
     pub viewporter_state: Option<ViewporterState<W>>,
+    /// presentation time
+    pub presentation_state: Option<PresentationTimeState<W>>,
     /// toplevel_info_state
     pub toplevel_info_state: Option<ToplevelInfoState>,
     /// toplevel_manager_state
@@ -133,6 +158,17 @@ pub struct ClientState<W: WrapperSpace + 'static> {
     pub workspace_state: Option<WorkspaceState>,
     /// security context manager
     pub security_context_manager: Option<SecurityContextManager>,
+    /// keyboard shortcuts inhibit manager
+    pub keyboard_shortcuts_inhibit_manager: Option<KeyboardShortcutsInhibitManager<W>>,
+    /// idle inhibit manager
+    pub idle_inhibit_manager: Option<IdleInhibitManager<W>>,
+    /// xdg activation
+    pub activation_state: Option<ActivationState>,
+    pub(crate) dmabuf_state: DmabufState,
+    /// the default dmabuf feedback of the host, if it supports v4
+    pub(crate) dmabuf_feedback: Option<DmabufFeedback>,
+    /// whether the host supports explicit sync
+    pub(crate) host_drm_syncobj: bool,
 
     pub(crate) connection: Connection,
     /// queue handle
@@ -144,8 +180,20 @@ pub struct ClientState<W: WrapperSpace + 'static> {
     pub(crate) cursor_surface: Option<wl_surface::WlSurface>,
     pub(crate) multipool: Option<MultiPool<(WlSurface, usize)>>,
     pub(crate) multipool_ctr: usize,
-    pub(crate) last_key_pressed: Vec<(String, (u32, u32), wl_surface::WlSurface)>,
+    /// timestamp of the last host frame callback, and when it was received
+    pub(crate) last_frame: Option<(u32, Instant)>,
     pub(crate) outputs: Vec<(WlOutput, Output, GlobalId)>,
+    /// host inhibitors of the focused embedded surface of each seat, and the host surface they
+    /// inhibit shortcuts on
+    pub(crate) shortcuts_inhibitors: Vec<(
+        String,
+        KeyboardShortcutsInhibitor,
+        WlSurface,
+        ZwpKeyboardShortcutsInhibitorV1,
+    )>,
+    /// host idle inhibitors of the visible embedded surfaces which inhibit idling, and the host
+    /// surface they are created on
+    pub(crate) idle_inhibitors: Vec<(s_WlSurface, WlSurface, ZwpIdleInhibitorV1)>,
 
     pub(crate) pending_layer_surfaces: Vec<(
         smithay::wayland::shell::wlr_layer::LayerSurface,
@@ -161,6 +209,8 @@ pub struct ClientState<W: WrapperSpace + 'static> {
         f64,
         Option<WpFractionalScaleV1>,
         Option<WpViewport>,
+        // the host output the layer surface was created for, if any
+        Option<wl_output::WlOutput>,
     )>,
 }
 
@@ -180,8 +230,18 @@ impl<W: WrapperSpace + std::fmt::Debug> Debug for ClientState<W> {
                 &self.fractional_scaling_manager,
             )
             .field("viewporter_state", &self.viewporter_state)
+            .field("presentation_state", &self.presentation_state)
+            .field(
+                "keyboard_shortcuts_inhibit_manager",
+                &self.keyboard_shortcuts_inhibit_manager,
+            )
+            .field("idle_inhibit_manager", &self.idle_inhibit_manager)
+            .field("activation_state", &self.activation_state)
             .field("toplevel_info_state", &self.toplevel_info_state)
             .field("toplevel_manager_state", &())
+            .field("dmabuf_state", &self.dmabuf_state)
+            .field("dmabuf_feedback", &self.dmabuf_feedback)
+            .field("host_drm_syncobj", &self.host_drm_syncobj)
             .field("connection", &self.connection)
             .field("queue_handle", &self.queue_handle)
             .field("focused_surface", &self.focused_surface)
@@ -189,8 +249,10 @@ impl<W: WrapperSpace + std::fmt::Debug> Debug for ClientState<W> {
             .field("cursor_surface", &self.cursor_surface)
             .field("multipool", &self.multipool)
             .field("multipool_ctr", &self.multipool_ctr)
-            .field("last_key_pressed", &self.last_key_pressed)
+            .field("last_frame", &self.last_frame)
             .field("outputs", &self.outputs)
+            .field("shortcuts_inhibitors", &self.shortcuts_inhibitors)
+            .field("idle_inhibitors", &self.idle_inhibitors)
             .field("pending_layer_surfaces", &self.pending_layer_surfaces)
             .field("proxied_layer_surfaces", &self.proxied_layer_surfaces)
             .finish()
@@ -202,14 +264,69 @@ impl<W: WrapperSpace + std::fmt::Debug> Debug for ClientState<W> {
 pub struct WrapperClientCompositorState {
     /// compositor state
     pub compositor_state: CompositorClientState,
+    /// metadata of the embedded client
+    pub metadata: ClientMetadata,
+    /// notifies the event loop when the client disconnects
+    pub(crate) disconnected: Option<calloop::channel::Sender<(ClientId, ClientMetadata)>>,
+    /// host security context of a sandboxed client, kept listening while the client is alive
+    pub(crate) security_context: Option<SecurityContextListener>,
+}
+
+impl WrapperClientCompositorState {
+    /// trust level of an embedded client
+    /// clients which weren't inserted by the wrapper are untrusted
+    pub fn trust(client: &Client) -> ClientTrust {
+        client
+            .get_data::<WrapperClientCompositorState>()
+            .map_or(ClientTrust::Untrusted, |data| data.metadata.trust)
+    }
+}
+
+/// metadata of an embedded client
+/// used to classify it when it binds globals, and to resolve the applet which owns a surface
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ClientMetadata {
+    /// trust level of the client
+    pub trust: ClientTrust,
+    /// name of the client
+    pub name: Option<String>,
+    /// app id of the client
+    pub app_id: Option<String>,
+    /// id of the config entry which the client was created for
+    pub config_id: Option<String>,
+    /// pid of the client process
+    pub pid: Option<u32>,
+    /// set for sandboxed applets, with the metadata of their security context on the host
+    pub sandbox: Option<SandboxInfo>,
 }
+
+/// how much an embedded client is trusted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ClientTrust {
+    /// the client may only bind globals which are required to embed it
+    Untrusted,
+    /// the client may bind all globals
+    #[default]
+    Trusted,
+}
+
 impl ClientData for WrapperClientCompositorState {
     /// Notification that a client was initialized
     fn initialized(&self, _client_id: ClientId) {}
     /// Notification that a client is disconnected
-    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    fn disconnected(&self, client_id: ClientId, _reason: DisconnectReason) {
+        // the state of the client is purged in the event loop, where it is accessible
+        if let Some(disconnected) = self.disconnected.as_ref() {
+            let _ = disconnected.send((client_id, self.metadata.clone()));
+        }
+    }
 }
 
+/// refresh interval used for outputs which don't report a refresh rate
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_nanos(16_666_667);
+/// interval of frame callbacks for surfaces which aren't visible on any output
+const FALLBACK_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum SurfaceState {
     WaitingFirst,
@@ -250,6 +367,13 @@ impl<W: WrapperSpace + 'static> ClientState<W> {
                     (None, None)
                 }
             };
+        let presentation_state = match PresentationTimeState::new(&globals, &qh) {
+            Ok(s) => Some(s),
+            Err(why) => {
+                error!(?why, "Failed to initialize presentation time");
+                None
+            }
+        };
         let security_context_manager = match SecurityContextManager::new(&globals, &qh) {
             Err(why) => {
                 error!(?why, "Failed to initialize security context manager");
@@ -257,6 +381,44 @@ impl<W: WrapperSpace + 'static> ClientState<W> {
             }
             Ok(m) => Some(m),
         };
+        let keyboard_shortcuts_inhibit_manager =
+            match KeyboardShortcutsInhibitManager::new(&globals, &qh) {
+                Ok(m) => Some(m),
+                Err(why) => {
+                    error!(
+                        ?why,
+                        "Failed to initialize keyboard shortcuts inhibit manager"
+                    );
+                    None
+                }
+            };
+        let idle_inhibit_manager = match IdleInhibitManager::new(&globals, &qh) {
+            Ok(m) => Some(m),
+            Err(why) => {
+                error!(?why, "Failed to initialize idle inhibit manager");
+                None
+            }
+        };
+        let activation_state = match ActivationState::bind(&globals, &qh) {
+            Ok(s) => Some(s),
+            Err(why) => {
+                error!(?why, "Failed to initialize xdg activation");
+                None
+            }
+        };
+
+        let dmabuf_state = DmabufState::new(&globals, &qh);
+        // the default feedback is delivered through the `DmabufHandler`
+        if let Err(why) = dmabuf_state.get_default_feedback(&qh) {
+            error!(?why, "Failed to request default dmabuf feedback");
+        }
+
+        // the wrapper's own surfaces are synchronized with the host by EGL, so only the presence of
+        // the global matters
+        let host_drm_syncobj = globals.contents().with_list(|list| {
+            list.iter()
+                .any(|global| global.interface == "wp_linux_drm_syncobj_manager_v1")
+        });
 
         let client_state = ClientState {
             focused_surface: space.get_client_focused_surface(),
@@ -280,13 +442,22 @@ impl<W: WrapperSpace + 'static> ClientState<W> {
             multipool: None,
             multipool_ctr: 0,
             cursor_surface: None,
-            last_key_pressed: Vec::new(),
+            last_frame: None,
             fractional_scaling_manager,
             viewporter_state,
+            presentation_state,
             toplevel_info_state: None,
             toplevel_manager_state: None,
             workspace_state: None,
             security_context_manager: security_context_manager,
+            keyboard_shortcuts_inhibit_manager,
+            shortcuts_inhibitors: Vec::new(),
+            idle_inhibit_manager,
+            idle_inhibitors: Vec::new(),
+            activation_state,
+            dmabuf_state,
+            dmabuf_feedback: None,
+            host_drm_syncobj,
         };
 
         WaylandSource::new(connection, event_queue)
@@ -296,10 +467,99 @@ impl<W: WrapperSpace + 'static> ClientState<W> {
         Ok(client_state)
     }
 
+    /// the refresh interval of the current mode of a host output
+    pub(crate) fn refresh_interval(&self, c_output: &WlOutput) -> Duration {
+        self.output_state
+            .info(c_output)
+            .and_then(|info| {
+                info.modes
+                    .iter()
+                    .find(|m| m.current)
+                    .map(|m| m.refresh_rate)
+            })
+            .filter(|refresh_rate| *refresh_rate > 0)
+            // refresh rates are in mHz
+            .map(|refresh_rate| Duration::from_secs_f64(1_000.0 / refresh_rate as f64))
+            .unwrap_or(DEFAULT_REFRESH_INTERVAL)
+    }
+
+    /// the tracked outputs which a host surface is currently visible on
+    pub(crate) fn visible_outputs(
+        &self,
+        c_surface: &wl_surface::WlSurface,
+    ) -> Vec<&(WlOutput, Output, GlobalId)> {
+        let Some(data) = c_surface.data::<SurfaceData>() else {
+            return Vec::new();
+        };
+        let entered: Vec<_> = data.outputs().collect();
+        self.outputs
+            .iter()
+            .filter(|o| entered.contains(&o.0))
+            .collect()
+    }
+
+    /// estimate of the current host time, based on the last host frame callback
+    pub(crate) fn host_time(&self) -> Option<u32> {
+        self.last_frame.map(|(time, received)| {
+            time.wrapping_add(
+                received
+                    .elapsed()
+                    .as_millis()
+                    .try_into()
+                    .unwrap_or(u32::MAX),
+            )
+        })
+    }
+
+    /// the embedded output which presentation feedback of a host surface is reported for
+    /// it is the output the host surface is visible on which also throttles its frame callbacks
+    pub(crate) fn presentation_output(&self, c_surface: &wl_surface::WlSurface) -> Option<Output> {
+        self.visible_outputs(c_surface)
+            .into_iter()
+            .min_by_key(|o| self.refresh_interval(&o.0))
+            .map(|o| o.1.clone())
+    }
+
+    /// send frame callbacks to an embedded surface tree which is displayed on a host surface
+    /// callbacks are throttled to the refresh rate of the fastest output the host surface is
+    /// visible on, or to a low fallback rate if it isn't visible on any output
+    pub fn send_frames(
+        &self,
+        c_surface: &wl_surface::WlSurface,
+        s_surface: &s_WlSurface,
+        time: u32,
+    ) {
+        let target = self
+            .visible_outputs(c_surface)
+            .into_iter()
+            .map(|o| {
+                // host timestamps only have millisecond precision
+                let throttle = self
+                    .refresh_interval(&o.0)
+                    .saturating_sub(Duration::from_millis(1));
+                (o, throttle)
+            })
+            .min_by_key(|(_, throttle)| *throttle);
+        let (output, throttle) = match target {
+            Some((o, throttle)) => (&o.1, throttle),
+            None => match self.outputs.first() {
+                Some(o) => (&o.1, FALLBACK_FRAME_INTERVAL),
+                None => return,
+            },
+        };
+        send_frames_surface_tree(
+            s_surface,
+            output,
+            Duration::from_millis(time as u64),
+            Some(throttle),
+            |_, _| None,
+        );
+    }
+
     /// draw the proxied layer shell surfaces
-    pub fn draw_layer_surfaces(&mut self, renderer: &mut GlesRenderer, time: u32) {
+    pub fn draw_layer_surfaces(&mut self, renderer: &mut GlesRenderer) {
         let clear_color = &[0.0, 0.0, 0.0, 0.0];
-        for (egl_surface, dmg_tracked_renderer, s_layer, _, state, _, _, _) in
+        for (egl_surface, dmg_tracked_renderer, s_layer, c_layer, state, ..) in
             &mut self.proxied_layer_surfaces
         {
             match state {
@@ -319,6 +579,24 @@ impl<W: WrapperSpace + 'static> ClientState<W> {
                     *clear_color,
                 )
                 .unwrap();
+            // feedback must be requested before the host surface is committed by the swap
+            // the flags are relayed from the host once it has presented the frame
+            if let (Some(presentation_state), Some(output)) = (
+                self.presentation_state.as_ref(),
+                self.presentation_output(c_layer.wl_surface()),
+            ) {
+                let mut feedback = OutputPresentationFeedback::new(&output);
+                s_layer.take_presentation_feedback(
+                    &mut feedback,
+                    |_, _| Some(output.clone()),
+                    |_, _| wp_presentation_feedback::Kind::empty(),
+                );
+                presentation_state.feedback(c_layer.wl_surface(), feedback, &self.queue_handle);
+            }
+            // embedded frame callbacks are sent when the host frame callback is received
+            c_layer
+                .wl_surface()
+                .frame(&self.queue_handle, c_layer.wl_surface().clone());
             egl_surface.swap_buffers(None).unwrap();
             // FIXME: damage tracking issues on integrated graphics but not nvidia
             // self.egl_surface
@@ -327,16 +605,6 @@ impl<W: WrapperSpace + 'static> ClientState<W> {
             //     .swap_buffers(res.0.as_deref_mut())?;
 
             renderer.unbind().unwrap();
-            // TODO what if there is "no output"?
-            for o in &self.outputs {
-                let output = &o.1;
-                s_layer.send_frame(
-                    &o.1,
-                    Duration::from_millis(time as u64),
-                    None,
-                    move |_, _| Some(output.clone()),
-                )
-            }
             *state = SurfaceState::Waiting;
         }
     }
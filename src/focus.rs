@@ -0,0 +1,262 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use smithay::{
+    reexports::wayland_server::{
+        backend::ClientId, protocol::wl_surface::WlSurface as s_WlSurface, Resource,
+    },
+    utils::SERIAL_COUNTER,
+};
+
+use crate::{client_state::FocusStatus, shared_state::GlobalState, space::WrapperSpace};
+
+/// when embedded surfaces receive keyboard focus from the pointer
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FocusPolicy {
+    /// the clicked surface is focused, and clicking outside of an embedded surface clears focus
+    #[default]
+    Click,
+    /// the clicked surface is focused, and keeps focus when clicking outside of an embedded
+    /// surface
+    ClickRetain,
+    /// the hovered surface is focused
+    Hover,
+}
+
+/// the reason for a keyboard focus change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusReason {
+    /// a button was pressed
+    Click,
+    /// the pointer moved over a surface
+    Hover,
+    /// the host keyboard entered a surface of the wrapper
+    Enter,
+    /// the host keyboard left a surface of the wrapper
+    Leave,
+    /// the focused surface was destroyed, and focus returns to the previously focused surface
+    Restore,
+    /// the focus was requested by the space
+    Request,
+}
+
+/// keyboard focus of a seat
+#[derive(Debug, Default)]
+struct SeatFocus {
+    name: String,
+    focused: Option<s_WlSurface>,
+    /// previously focused surfaces, the most recently focused last
+    stack: Vec<s_WlSurface>,
+    /// clients of the focused and previously focused surfaces
+    /// the client of a surface can't be resolved anymore once it disconnected
+    clients: Vec<(s_WlSurface, ClientId)>,
+}
+
+/// keyboard focus of the embedded surfaces of each seat
+#[derive(Debug, Default)]
+pub struct FocusManager {
+    seats: Vec<SeatFocus>,
+}
+
+impl FocusManager {
+    /// the focused embedded surface of a seat
+    pub fn focused(&self, seat_name: &str) -> Option<&s_WlSurface> {
+        self.seat(seat_name).and_then(|s| s.focused.as_ref())
+    }
+
+    /// the previously focused embedded surfaces of a seat, the most recently focused last
+    pub fn stack(&self, seat_name: &str) -> &[s_WlSurface] {
+        self.seat(seat_name).map_or(&[], |s| s.stack.as_slice())
+    }
+
+    fn seat(&self, seat_name: &str) -> Option<&SeatFocus> {
+        self.seats.iter().find(|s| s.name == seat_name)
+    }
+
+    fn seat_mut(&mut self, seat_name: &str) -> &mut SeatFocus {
+        if let Some(i) = self.seats.iter().position(|s| s.name == seat_name) {
+            &mut self.seats[i]
+        } else {
+            self.seats.push(SeatFocus {
+                name: seat_name.to_string(),
+                ..Default::default()
+            });
+            self.seats.last_mut().unwrap()
+        }
+    }
+
+    fn set_focused(&mut self, seat_name: &str, surface: Option<s_WlSurface>) {
+        let seat = self.seat_mut(seat_name);
+        if let Some(old) = seat.focused.take() {
+            seat.stack.retain(|s| s != &old);
+            seat.stack.push(old);
+        }
+        if let Some(surface) = surface.as_ref() {
+            seat.stack.retain(|s| s != surface);
+            if let Some(client) = surface.client() {
+                seat.clients.retain(|(s, _)| s != surface);
+                seat.clients.push((surface.clone(), client.id()));
+            }
+        }
+        seat.focused = surface;
+    }
+
+    /// forget the surfaces of a disconnected client
+    /// returns the seats whose focused surface belonged to the client
+    fn purge_client(&mut self, client_id: &ClientId) -> Vec<String> {
+        let mut unfocused = Vec::new();
+        for seat in &mut self.seats {
+            let surfaces: Vec<_> = seat
+                .clients
+                .iter()
+                .filter(|(_, id)| id == client_id)
+                .map(|(s, _)| s.clone())
+                .collect();
+            if surfaces.is_empty() {
+                continue;
+            }
+            seat.clients.retain(|(_, id)| id != client_id);
+            seat.stack.retain(|s| !surfaces.contains(s));
+            if seat.focused.as_ref().is_some_and(|s| surfaces.contains(s)) {
+                seat.focused = None;
+                unfocused.push(seat.name.clone());
+            }
+        }
+        unfocused
+    }
+}
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// the keyboard focus manager of the embedded surfaces
+    pub fn focus_manager(&self) -> &FocusManager {
+        &self.focus_manager
+    }
+
+    /// change the keyboard focus of a seat
+    /// the space is consulted before the pointer changes the focus, and layer surfaces which don't
+    /// accept keyboard focus are never focused
+    /// returns whether the focus changed
+    pub fn set_keyboard_focus(
+        &mut self,
+        seat_name: &str,
+        surface: Option<s_WlSurface>,
+        reason: FocusReason,
+    ) -> bool {
+        let current = self.focus_manager.focused(seat_name).cloned();
+        if current == surface {
+            return false;
+        }
+        if surface
+            .as_ref()
+            .is_some_and(|s| !self.accepts_keyboard_focus(s))
+        {
+            return false;
+        }
+        // the host keyboard entering or leaving the wrapper always changes the focus
+        if matches!(reason, FocusReason::Click | FocusReason::Hover)
+            && !self
+                .space
+                .allow_focus_change(seat_name, current.as_ref(), surface.as_ref(), reason)
+        {
+            return false;
+        }
+        let Some(kbd) = self
+            .server_state
+            .seats
+            .iter()
+            .find(|s| s.name == seat_name)
+            .and_then(|s| s.server.seat.get_keyboard())
+        else {
+            return false;
+        };
+
+        self.focus_manager.set_focused(seat_name, surface.clone());
+        kbd.set_focus(self, surface, SERIAL_COUNTER.next_serial());
+        self.update_shortcuts_inhibitor(seat_name);
+        true
+    }
+
+    /// focus the surface under the pointer after a button press, according to the focus policy
+    pub(crate) fn focus_clicked(&mut self, seat_name: &str, surface: Option<s_WlSurface>) {
+        if surface.is_some() || self.space.focus_policy() == FocusPolicy::Click {
+            self.set_keyboard_focus(seat_name, surface, FocusReason::Click);
+        }
+    }
+
+    /// focus the surface under the pointer, if the focus policy follows the pointer
+    pub(crate) fn focus_hovered(&mut self, seat_name: &str, surface: &s_WlSurface) {
+        if self.space.focus_policy() == FocusPolicy::Hover {
+            self.set_keyboard_focus(seat_name, Some(surface.clone()), FocusReason::Hover);
+        }
+    }
+
+    /// return the focus of each seat whose focused surface was destroyed to the most recently
+    /// focused surface which is still alive
+    pub(crate) fn refresh_keyboard_focus(&mut self) {
+        let mut unfocused = Vec::new();
+        for seat in &mut self.focus_manager.seats {
+            seat.stack.retain(|s| s.is_alive());
+            seat.clients.retain(|(s, _)| s.is_alive());
+            if seat.focused.as_ref().is_some_and(|s| !s.is_alive()) {
+                seat.focused = None;
+                unfocused.push(seat.name.clone());
+            }
+        }
+        self.restore_keyboard_focus(unfocused);
+    }
+
+    /// forget the surfaces of a disconnected client, and return the focus of each seat whose
+    /// focused surface belonged to it to the most recently focused surface of another client
+    pub(crate) fn purge_client_focus(&mut self, client_id: &ClientId) {
+        let unfocused = self.focus_manager.purge_client(client_id);
+        self.restore_keyboard_focus(unfocused);
+    }
+
+    /// focus the most recently focused surface of each seat which lost its focused surface
+    fn restore_keyboard_focus(&mut self, seat_names: Vec<String>) {
+        if seat_names.is_empty() {
+            return;
+        }
+        let mut restore = Vec::new();
+        let host_focused = self.client_state.focused_surface.borrow();
+        for seat_name in seat_names {
+            // focus is only restored while the host keyboard is on a surface of the wrapper
+            let has_host_focus = host_focused
+                .iter()
+                .any(|f| f.1 == seat_name && matches!(f.2, FocusStatus::Focused));
+            let surface = if has_host_focus {
+                self.focus_manager.seat_mut(&seat_name).stack.pop()
+            } else {
+                None
+            };
+            restore.push((seat_name, surface));
+        }
+        drop(host_focused);
+        for (seat_name, surface) in restore {
+            if !self.set_keyboard_focus(&seat_name, surface, FocusReason::Restore) {
+                // the focus of the keyboard must not be left on a dead surface
+                if let Some(kbd) = self
+                    .server_state
+                    .seats
+                    .iter()
+                    .find(|s| s.name == seat_name)
+                    .and_then(|s| s.server.seat.get_keyboard())
+                {
+                    kbd.set_focus(self, None, SERIAL_COUNTER.next_serial());
+                }
+                self.update_shortcuts_inhibitor(&seat_name);
+            }
+        }
+    }
+
+    /// whether an embedded surface may receive keyboard focus
+    /// follows the keyboard interactivity of proxied layer surfaces
+    fn accepts_keyboard_focus(&self, surface: &s_WlSurface) -> bool {
+        self.client_state
+            .proxied_layer_surfaces
+            .iter()
+            .find(|(_, _, s_layer, ..)| s_layer.wl_surface() == surface)
+            .map_or(true, |(_, _, s_layer, ..)| {
+                s_layer.can_receive_keyboard_focus()
+            })
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use smithay::input::keyboard::{xkb, KeysymHandle, ModifiersState};
+use tracing::warn;
+pub use xdg_shell_wrapper_config::{KeyBinding, KeyModifiers};
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+/// key bindings which are evaluated before keys are forwarded to embedded clients
+#[derive(Debug, Default)]
+pub struct KeyBindings {
+    bindings: Vec<(xkb::Keysym, KeyBinding)>,
+}
+
+impl KeyBindings {
+    /// replace the key bindings
+    /// bindings with an unknown keysym name are ignored
+    pub fn set(&mut self, bindings: Vec<KeyBinding>) {
+        self.bindings = bindings
+            .into_iter()
+            .filter_map(|binding| {
+                let keysym = xkb::keysym_from_name(&binding.key, xkb::KEYSYM_CASE_INSENSITIVE);
+                if keysym.raw() == xkb::keysyms::KEY_NoSymbol {
+                    warn!("Ignoring key binding with unknown key {}", binding.key);
+                    None
+                } else {
+                    Some((keysym, binding))
+                }
+            })
+            .collect();
+    }
+
+    /// the key bindings
+    pub fn bindings(&self) -> impl Iterator<Item = &KeyBinding> {
+        self.bindings.iter().map(|(_, binding)| binding)
+    }
+
+    /// the binding matched by a pressed key and the held modifiers
+    /// keysyms are matched without the shift level applied, and a modifier key doesn't need to
+    /// hold its own modifier, so `Super_L` without modifiers matches a press of Super
+    pub fn find(
+        &self,
+        modifiers: &ModifiersState,
+        keysym: &KeysymHandle<'_>,
+    ) -> Option<&KeyBinding> {
+        let raw_syms = keysym.raw_syms();
+        self.bindings
+            .iter()
+            .find(|(sym, binding)| {
+                if !raw_syms.contains(sym) {
+                    return false;
+                }
+                let mut held = KeyModifiers {
+                    ctrl: modifiers.ctrl,
+                    alt: modifiers.alt,
+                    shift: modifiers.shift,
+                    logo: modifiers.logo,
+                };
+                let name = xkb::keysym_get_name(*sym);
+                if name.starts_with("Control_") {
+                    held.ctrl = binding.modifiers.ctrl;
+                } else if name.starts_with("Alt_") || name.starts_with("Meta_") {
+                    held.alt = binding.modifiers.alt;
+                } else if name.starts_with("Shift_") {
+                    held.shift = binding.modifiers.shift;
+                } else if name.starts_with("Super_") {
+                    held.logo = binding.modifiers.logo;
+                }
+                held == binding.modifiers
+            })
+            .map(|(_, binding)| binding)
+    }
+}
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// the key bindings which are currently evaluated
+    pub fn keybindings(&self) -> &KeyBindings {
+        &self.keybindings
+    }
+
+    /// reload the key bindings from the space
+    /// called after setup and when a new config is applied
+    pub fn reload_keybindings(&mut self) {
+        let bindings = self.space.keybindings();
+        self.keybindings.set(bindings);
+    }
+}
@@ -0,0 +1,403 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    io,
+    os::{
+        fd::AsRawFd,
+        unix::{net::UnixStream, process::CommandExt},
+    },
+    process::{Child, Command, ExitStatus},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use rustix::process::{kill_process, pidfd_open, Pid, PidfdFlags, Signal};
+use smithay::reexports::{
+    calloop::{
+        channel::Sender,
+        generic::Generic,
+        timer::{TimeoutAction, Timer},
+        Interest, LoopHandle, Mode, PostAction,
+    },
+    wayland_server::{backend::ClientId, Client, DisplayHandle},
+};
+use tracing::{error, info};
+
+use crate::{
+    client_state::{ClientMetadata, ClientTrust, WrapperClientCompositorState},
+    shared_state::GlobalState,
+    space::WrapperSpace,
+    wp_security_context::SecurityContextListener,
+};
+
+/// delay before the first restart of a client which exits quickly
+const RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// maximum delay between restarts of a client which keeps exiting quickly
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+/// a client which ran for longer than this before exiting is restarted without a backoff
+const RESTART_BACKOFF_RESET: Duration = Duration::from_secs(60);
+
+/// when a launched client is restarted after it exits
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// the client is never restarted
+    #[default]
+    Never,
+    /// the client is restarted if it fails, with an exponential backoff if it exits quickly
+    OnFailure,
+    /// the client is always restarted, with an exponential backoff if it exits quickly
+    Always,
+}
+
+impl RestartPolicy {
+    /// delay before a client is restarted, if it should be
+    /// `quick_exits` is the number of consecutive quick exits of the client, including this exit
+    fn restart_delay(&self, status: ExitStatus, quick_exits: u32) -> Option<Duration> {
+        match (self, status.success()) {
+            (RestartPolicy::Never, _) | (RestartPolicy::OnFailure, true) => None,
+            _ if quick_exits == 0 => Some(Duration::ZERO),
+            _ => Some(
+                RESTART_BACKOFF_BASE
+                    .saturating_mul(2u32.saturating_pow(quick_exits - 1))
+                    .min(RESTART_BACKOFF_MAX),
+            ),
+        }
+    }
+}
+
+/// an embedded client which can be launched by the wrapper
+#[derive(Debug, Clone)]
+pub struct ClientCommand {
+    /// id of the client, passed to the space when it exits or is restarted
+    pub id: String,
+    /// `Exec` style command line of the client, ex. `cosmic-applet-audio --verbose`
+    pub exec: String,
+    /// extra environment variables of the client
+    pub env: Vec<(String, String)>,
+    /// restart policy of the client
+    pub restart: RestartPolicy,
+    /// metadata which the client is inserted with
+    pub metadata: ClientMetadata,
+}
+
+/// a client launched by a [`ClientLauncher`]
+/// the handle follows the client across restarts
+#[derive(Debug, Clone)]
+pub struct LaunchedClient {
+    id: String,
+    inner: Rc<RefCell<LaunchedClientInner>>,
+}
+
+#[derive(Debug, Default)]
+struct LaunchedClientInner {
+    /// the process of the client, until it is reaped
+    child: Option<Child>,
+    /// the embedded client of the process
+    client: Option<Client>,
+    /// the client was stopped, and is not restarted anymore
+    stopped: bool,
+}
+
+impl LaunchedClient {
+    /// id of the client
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// the embedded client, if the client is running
+    pub fn client(&self) -> Option<Client> {
+        self.inner.borrow().client.clone()
+    }
+
+    /// pid of the client process, if the client is running
+    pub fn pid(&self) -> Option<u32> {
+        self.inner.borrow().child.as_ref().map(Child::id)
+    }
+
+    /// whether the client was stopped or killed
+    pub fn is_stopped(&self) -> bool {
+        self.inner.borrow().stopped
+    }
+
+    /// ask the client to exit with SIGTERM
+    /// the client is not restarted anymore, regardless of its restart policy
+    pub fn stop(&self) -> io::Result<()> {
+        self.signal(Signal::Term)
+    }
+
+    /// kill the client with SIGKILL
+    /// the client is not restarted anymore, regardless of its restart policy
+    pub fn kill(&self) -> io::Result<()> {
+        self.signal(Signal::Kill)
+    }
+
+    fn signal(&self, signal: Signal) -> io::Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        inner.stopped = true;
+        match inner.child.as_ref() {
+            // the process isn't reaped yet, so its pid can't have been reused
+            Some(child) => Ok(kill_process(Pid::from_child(child), signal)?),
+            None => Ok(()),
+        }
+    }
+}
+
+/// inserts embedded clients, and launches and supervises them on the event loop
+pub struct ClientLauncher<W: WrapperSpace + 'static> {
+    loop_handle: LoopHandle<'static, GlobalState<W>>,
+    display: DisplayHandle,
+    disconnected: Sender<(ClientId, ClientMetadata)>,
+}
+
+impl<W: WrapperSpace + 'static> Debug for ClientLauncher<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientLauncher")
+            .field("display", &self.display)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: WrapperSpace + 'static> Clone for ClientLauncher<W> {
+    fn clone(&self) -> Self {
+        Self {
+            loop_handle: self.loop_handle.clone(),
+            display: self.display.clone(),
+            disconnected: self.disconnected.clone(),
+        }
+    }
+}
+
+impl<W: WrapperSpace + 'static> ClientLauncher<W> {
+    pub(crate) fn new(
+        loop_handle: LoopHandle<'static, GlobalState<W>>,
+        display: DisplayHandle,
+        disconnected: Sender<(ClientId, ClientMetadata)>,
+    ) -> Self {
+        Self {
+            loop_handle,
+            display,
+            disconnected,
+        }
+    }
+
+    /// insert a wrapped applet client
+    pub fn get_client_sock(&self) -> (Client, UnixStream) {
+        self.get_client_sock_with_metadata(ClientMetadata::default())
+    }
+
+    /// insert a sandboxed wrapped applet client
+    /// the client is untrusted, so privileged embedded globals are hidden from it, matching the
+    /// restrictions of the security context it is given on the host
+    /// the listener of the security context is kept until the client disconnects, so the host
+    /// connection taken from it stays valid until the applet has connected
+    pub fn get_sandboxed_client_sock(
+        &self,
+        security_context: SecurityContextListener,
+    ) -> (Client, UnixStream) {
+        let sandbox = security_context.sandbox().clone();
+        let metadata = ClientMetadata {
+            trust: ClientTrust::Untrusted,
+            app_id: Some(sandbox.app_id.clone()),
+            sandbox: Some(sandbox),
+            ..Default::default()
+        };
+        let (display_sock, client_sock) = UnixStream::pair().unwrap();
+
+        (
+            self.insert(display_sock, metadata, Some(security_context))
+                .unwrap(),
+            client_sock,
+        )
+    }
+
+    /// insert a wrapped applet client with metadata
+    /// the metadata decides which embedded globals the client can bind
+    pub fn get_client_sock_with_metadata(&self, metadata: ClientMetadata) -> (Client, UnixStream) {
+        let (display_sock, client_sock) = UnixStream::pair().unwrap();
+
+        (
+            self.insert_client(display_sock, metadata).unwrap(),
+            client_sock,
+        )
+    }
+
+    /// insert a client connected through the given stream, with metadata
+    /// the metadata can be resolved from the embedded surfaces of the client later, and the state
+    /// of the client is purged once it disconnects
+    pub fn insert_client(
+        &self,
+        stream: UnixStream,
+        metadata: ClientMetadata,
+    ) -> io::Result<Client> {
+        self.insert(stream, metadata, None)
+    }
+
+    fn insert(
+        &self,
+        stream: UnixStream,
+        metadata: ClientMetadata,
+        security_context: Option<SecurityContextListener>,
+    ) -> io::Result<Client> {
+        self.display.clone().insert_client(
+            stream,
+            Arc::new(WrapperClientCompositorState {
+                compositor_state: Default::default(),
+                metadata,
+                disconnected: Some(self.disconnected.clone()),
+                security_context,
+            }),
+        )
+    }
+
+    /// launch a client connected to the embedded server
+    /// the space is notified through [`WrapperSpace::client_exited`] when it exits, and through
+    /// [`WrapperSpace::client_restarted`] when it is restarted according to its restart policy
+    /// the returned handle may be used to stop the client
+    pub fn launch(&self, command: ClientCommand) -> io::Result<LaunchedClient> {
+        let handle = LaunchedClient {
+            id: command.id.clone(),
+            inner: Default::default(),
+        };
+        self.spawn(command, 0, handle.clone())?;
+        Ok(handle)
+    }
+
+    fn spawn(
+        &self,
+        command: ClientCommand,
+        quick_exits: u32,
+        handle: LaunchedClient,
+    ) -> io::Result<Client> {
+        let mut args = shlex::split(&command.exec).unwrap_or_default().into_iter();
+        let Some(program) = args.next() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid exec for client {}: {}", command.id, command.exec),
+            ));
+        };
+
+        let (display_sock, client_sock) = UnixStream::pair()?;
+        let fd = client_sock.as_raw_fd();
+
+        let mut process = Command::new(program);
+        process
+            .args(args)
+            .envs(command.env.iter().map(|(k, v)| (k, v)))
+            .env_remove("WAYLAND_DISPLAY")
+            .env("WAYLAND_SOCKET", fd.to_string());
+        // the socket is inherited by the client
+        unsafe {
+            process.pre_exec(move || {
+                if libc::fcntl(fd, libc::F_SETFD, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let mut child = process.spawn()?;
+        // the client owns its end of the socket now
+        drop(client_sock);
+
+        let pidfd = match pidfd_open(Pid::from_child(&child), PidfdFlags::empty()) {
+            Ok(pidfd) => pidfd,
+            Err(err) => {
+                // an unsupervised client would never be restarted or cleaned up
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err.into());
+            }
+        };
+        info!(id = %command.id, pid = child.id(), "Launched client");
+
+        // the client is inserted once it is spawned, so that its pid is known
+        let client = match self.insert_client(
+            display_sock,
+            ClientMetadata {
+                pid: Some(child.id()),
+                ..command.metadata.clone()
+            },
+        ) {
+            Ok(client) => client,
+            Err(err) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(err);
+            }
+        };
+        {
+            let mut inner = handle.inner.borrow_mut();
+            inner.child = Some(child);
+            inner.client = Some(client.clone());
+        }
+
+        let started = Instant::now();
+        let launcher = self.clone();
+        self.loop_handle
+            .insert_source(
+                Generic::new(pidfd, Interest::READ, Mode::Level),
+                move |_, _, state| {
+                    let mut inner = handle.inner.borrow_mut();
+                    let Some(c) = inner.child.as_mut() else {
+                        return Ok(PostAction::Remove);
+                    };
+                    let status = match c.try_wait() {
+                        Ok(Some(status)) => status,
+                        Ok(None) => return Ok(PostAction::Continue),
+                        Err(err) => {
+                            error!(id = %command.id, "Failed to wait on client: {}", err);
+                            return Ok(PostAction::Remove);
+                        }
+                    };
+                    inner.child = None;
+                    inner.client = None;
+                    let stopped = inner.stopped;
+                    // the space may stop the client when it is notified
+                    drop(inner);
+
+                    // clients which exit quickly are backed off regardless of their exit status
+                    let quick_exits = if started.elapsed() > RESTART_BACKOFF_RESET {
+                        0
+                    } else {
+                        quick_exits + 1
+                    };
+                    let restart = if stopped {
+                        None
+                    } else {
+                        command.restart.restart_delay(status, quick_exits)
+                    };
+                    info!(id = %command.id, ?status, ?restart, "Client exited");
+                    state.space.client_exited(&command.id, status, restart);
+
+                    if let Some(delay) = restart {
+                        let launcher = launcher.clone();
+                        let command = command.clone();
+                        let handle = handle.clone();
+                        if let Err(err) = launcher.loop_handle.clone().insert_source(
+                            Timer::from_duration(delay),
+                            move |_, _, state| {
+                                if handle.is_stopped() {
+                                    return TimeoutAction::Drop;
+                                }
+                                match launcher.spawn(command.clone(), quick_exits, handle.clone()) {
+                                    Ok(client) => state.space.client_restarted(&command.id, client),
+                                    Err(err) => {
+                                        error!(id = %command.id, "Failed to restart client: {}", err)
+                                    }
+                                }
+                                TimeoutAction::Drop
+                            },
+                        ) {
+                            error!("Failed to schedule client restart: {}", err);
+                        }
+                    }
+                    Ok(PostAction::Remove)
+                },
+            )
+            .map_err(|err| io::Error::from(err.error))?;
+
+        Ok(client)
+    }
+}
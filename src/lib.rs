@@ -3,22 +3,20 @@
 
 //! Provides the core functionality for cosmic-panel
 
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use anyhow::Result;
-use sctk::{reexports::client::Proxy, shm::multi::MultiPool};
-use smithay::{
-    backend::input::KeyState,
-    input::keyboard::FilterResult,
-    reexports::{calloop, wayland_server::Display},
-    utils::SERIAL_COUNTER,
-};
+use sctk::shm::multi::MultiPool;
+use smithay::reexports::{calloop, wayland_server::Display};
 
 use client::state::ClientState;
 pub use client::{
-    handlers::{output, wp_fractional_scaling, wp_security_context, wp_viewporter},
+    handlers::{
+        output, wp_fractional_scaling, wp_presentation, wp_security_context, wp_viewporter,
+    },
     state as client_state,
 };
+use launcher::ClientLauncher;
 pub use server::state as server_state;
 use server::state::ServerState;
 use shared_state::GlobalState;
@@ -26,6 +24,12 @@ use space::{Visibility, WrapperSpace};
 pub use xdg_shell_wrapper_config as config;
 
 mod client;
+/// keyboard focus of embedded surfaces
+pub mod focus;
+/// key bindings handled by the wrapper
+pub mod keybindings;
+/// launching of embedded clients
+pub mod launcher;
 mod server;
 /// shared state
 pub mod shared_state;
@@ -33,6 +37,8 @@ pub mod shared_state;
 pub mod space;
 /// utilities
 pub mod util;
+#[cfg(feature = "xwayland")]
+mod xwayland;
 
 /// run the cosmic panel xdg wrapper with the provided config
 pub fn run<W: WrapperSpace + 'static>(
@@ -47,7 +53,24 @@ pub fn run<W: WrapperSpace + 'static>(
     let s_dh = server_display.handle();
     space.set_display_handle(s_dh.clone());
 
-    let mut global_state = GlobalState::new(client_state, embedded_server_state, space, start);
+    // embedded clients notify the event loop when they disconnect, so that their state is purged
+    let (disconnected_tx, disconnected_rx) = calloop::channel::channel();
+    event_loop
+        .handle()
+        .insert_source(disconnected_rx, |event, _, state| {
+            if let calloop::channel::Event::Msg((client_id, metadata)) = event {
+                state.client_disconnected(client_id, metadata);
+            }
+        })
+        .map_err(|err| err.error)?;
+
+    let mut global_state = GlobalState::new(
+        client_state,
+        embedded_server_state,
+        space,
+        start,
+        event_loop.handle(),
+    );
 
     global_state.space.setup(
         &global_state.client_state.compositor_state,
@@ -56,11 +79,20 @@ pub fn run<W: WrapperSpace + 'static>(
             .fractional_scaling_manager
             .as_ref(),
         global_state.client_state.security_context_manager.clone(),
+        ClientLauncher::new(event_loop.handle(), s_dh.clone(), disconnected_tx),
         global_state.client_state.viewporter_state.as_ref(),
         &mut global_state.client_state.layer_state,
         &global_state.client_state.connection,
         &global_state.client_state.queue_handle,
     );
+    if let Some(presentation_state) = global_state.client_state.presentation_state.as_ref() {
+        global_state
+            .space
+            .presentation_time_ready(presentation_state);
+    }
+    global_state.reload_keybindings();
+    #[cfg(feature = "xwayland")]
+    global_state.start_xwayland();
 
     // // remove extra looping after launch-pad is integrated
     for _ in 0..10 {
@@ -80,61 +112,10 @@ pub fn run<W: WrapperSpace + 'static>(
 
     global_state.bind_display(&s_dh);
 
-    let mut last_cleanup = Instant::now();
-    let five_min = Duration::from_secs(300);
-
     // TODO find better place for this
     // let set_clipboard_once = Rc::new(Cell::new(false));
 
     loop {
-        // cleanup popup manager
-        if last_cleanup.elapsed() > five_min {
-            global_state.server_state.popup_manager.cleanup();
-            last_cleanup = Instant::now();
-        }
-
-        // handle funky keyboard state.
-        // if a client layer shell surface is closed, then it won't receive the release event
-        // then the client will keep receiving input
-        // so we send the release here instead
-        let press = if let Some((key_pressed, kbd)) = global_state
-            .client_state
-            .last_key_pressed
-            .iter()
-            .position(|(_, _, layer_shell_wl_surface)| !layer_shell_wl_surface.is_alive())
-            .and_then(|key_pressed| {
-                global_state
-                    .server_state
-                    .seats
-                    .iter()
-                    .find(|s| s.name == global_state.client_state.last_key_pressed[key_pressed].0)
-                    .and_then(|s| {
-                        s.server.seat.get_keyboard().map(|kbd| {
-                            (
-                                global_state
-                                    .client_state
-                                    .last_key_pressed
-                                    .remove(key_pressed),
-                                kbd,
-                            )
-                        })
-                    })
-            }) {
-            Some((key_pressed, kbd))
-        } else {
-            None
-        };
-        if let Some((key_pressed, kbd)) = press {
-            kbd.input::<(), _>(
-                &mut global_state,
-                key_pressed.1 .0,
-                KeyState::Released,
-                SERIAL_COUNTER.next_serial(),
-                key_pressed.1 .1.wrapping_add(1),
-                move |_, _modifiers, _keysym| FilterResult::Forward,
-            );
-        }
-
         // dispatch desktop client events
         let dur = if matches!(global_state.space.visibility(), Visibility::Hidden) {
             Duration::from_millis(100)
@@ -142,6 +123,9 @@ pub fn run<W: WrapperSpace + 'static>(
             Duration::from_millis(16)
         };
         event_loop.dispatch(dur, &mut global_state)?;
+        global_state.refresh_keyboard_focus();
+        global_state.update_surface_outputs();
+        global_state.update_idle_inhibitors();
 
         // rendering
         {
@@ -155,11 +139,9 @@ pub fn run<W: WrapperSpace + 'static>(
             );
         }
         if let Some(renderer) = global_state.space.renderer() {
-            global_state.client_state.draw_layer_surfaces(
-                renderer,
-                global_state.start_time.elapsed().as_millis().try_into()?,
-            );
+            global_state.client_state.draw_layer_surfaces(renderer);
         }
+        global_state.send_fallback_frames();
 
         // dispatch server events
         {
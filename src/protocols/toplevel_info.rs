@@ -288,6 +288,10 @@ fn send_toplevel_to_client<D>(
         + ToplevelInfoHandler
         + 'static,
 {
+    // only xdg toplevels have the attributes which are sent to clients
+    let Some(toplevel) = window.toplevel() else {
+        return;
+    };
     let mut state = window
         .user_data()
         .get::<ToplevelState>()
@@ -327,7 +331,7 @@ fn send_toplevel_to_client<D>(
         .lock()
         .unwrap();
     let mut changed = false;
-    with_states(window.toplevel().wl_surface(), |states| {
+    with_states(toplevel.wl_surface(), |states| {
         let attributes = states
             .data_map
             .get::<Mutex<XdgToplevelSurfaceRoleAttributes>>()
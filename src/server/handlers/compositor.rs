@@ -23,11 +23,14 @@ use smithay::{
         shm::{ShmHandler, ShmState},
     },
 };
+#[cfg(feature = "xwayland")]
+use smithay::{reexports::wayland_server::Resource, xwayland::XWaylandClientData};
 use tracing::{error, trace};
 use wayland_egl::WlEglSurface;
 
 use crate::{
     client_state::{SurfaceState, WrapperClientCompositorState},
+    server::handlers::{drm_syncobj::add_acquire_point_hook, xdg_shell::apply_size_constraints},
     shared_state::GlobalState,
     space::{ClientEglSurface, WrapperSpace},
 };
@@ -37,6 +40,10 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
         &mut self.server_state.compositor_state
     }
 
+    fn new_surface(&mut self, surface: &WlSurface) {
+        add_acquire_point_hook::<W>(surface);
+    }
+
     fn commit(&mut self, surface: &WlSurface) {
         let dh = self.server_state.display_handle.clone();
         let role = get_role(surface);
@@ -44,6 +51,18 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
 
         if role == "xdg_toplevel".into() {
             on_commit_buffer_handler::<GlobalState<W>>(surface);
+            // the size constraints of the toplevel may have changed
+            if let Some(toplevel) = self
+                .server_state
+                .xdg_shell_state
+                .toplevel_surfaces()
+                .iter()
+                .find(|t| t.wl_surface() == surface)
+            {
+                if apply_size_constraints(toplevel) && toplevel.is_initial_configure_sent() {
+                    toplevel.send_configure();
+                }
+            }
             self.space.dirty_window(&dh, surface)
         } else if role == "xdg_popup".into() {
             on_commit_buffer_handler::<GlobalState<W>>(surface);
@@ -183,6 +202,7 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
                     1.0,
                     scale,
                     viewport,
+                    output.map(|o| o.0.clone()),
                 ));
             }
             if let Some((
@@ -194,6 +214,7 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
                 scale,
                 _,
                 viewport,
+                _,
             )) = self
                 .client_state
                 .proxied_layer_surfaces
@@ -269,6 +290,16 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
                 self.draw_dnd_icon();
             }
         } else {
+            // the surfaces of X11 windows are associated with their window by Xwayland
+            #[cfg(feature = "xwayland")]
+            if surface
+                .client()
+                .is_some_and(|c| c.get_data::<XWaylandClientData>().is_some())
+            {
+                on_commit_buffer_handler::<GlobalState<W>>(surface);
+                self.space.dirty_window(&dh, surface);
+                return;
+            }
             trace!("{:?}", surface);
         }
     }
@@ -277,6 +308,10 @@ impl<W: WrapperSpace> CompositorHandler for GlobalState<W> {
         &self,
         client: &'a smithay::reexports::wayland_server::Client,
     ) -> &'a smithay::wayland::compositor::CompositorClientState {
+        #[cfg(feature = "xwayland")]
+        if let Some(data) = client.get_data::<XWaylandClientData>() {
+            return &data.compositor_state;
+        }
         &client
             .get_data::<WrapperClientCompositorState>()
             .unwrap()
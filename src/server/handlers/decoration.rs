@@ -0,0 +1,86 @@
+use smithay::{
+    delegate_kde_decoration, delegate_xdg_decoration,
+    reexports::{
+        wayland_protocols::xdg::decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+        wayland_protocols_misc::server_decoration::server::org_kde_kwin_server_decoration::{
+            Mode as KdeMode, OrgKdeKwinServerDecoration,
+        },
+        wayland_server::{protocol::wl_surface::WlSurface, WEnum},
+    },
+    wayland::shell::{
+        kde::decoration::{KdeDecorationHandler, KdeDecorationState},
+        xdg::{decoration::XdgDecorationHandler, ToplevelSurface},
+    },
+};
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+impl<W: WrapperSpace> GlobalState<W> {
+    /// apply the decoration mode chosen by the space to an embedded toplevel
+    fn configure_decoration(
+        &mut self,
+        toplevel: &ToplevelSurface,
+        requested: Option<DecorationMode>,
+    ) {
+        let mode = self.space.decoration_mode(toplevel.wl_surface(), requested);
+        toplevel.with_pending_state(|state| state.decoration_mode = Some(mode));
+        // toplevels are configured as soon as they are created, so the mode is sent in another
+        // configure
+        if toplevel.is_initial_configure_sent() {
+            toplevel.send_configure();
+        }
+    }
+}
+
+impl<W: WrapperSpace> XdgDecorationHandler for GlobalState<W> {
+    fn new_decoration(&mut self, toplevel: ToplevelSurface) {
+        self.configure_decoration(&toplevel, None);
+    }
+
+    fn request_mode(&mut self, toplevel: ToplevelSurface, mode: DecorationMode) {
+        self.configure_decoration(&toplevel, Some(mode));
+    }
+
+    fn unset_mode(&mut self, toplevel: ToplevelSurface) {
+        self.configure_decoration(&toplevel, None);
+    }
+}
+
+impl<W: WrapperSpace> KdeDecorationHandler for GlobalState<W> {
+    fn kde_decoration_state(&self) -> &KdeDecorationState {
+        &self.server_state.kde_decoration_state
+    }
+
+    fn new_decoration(&mut self, surface: &WlSurface, decoration: &OrgKdeKwinServerDecoration) {
+        let mode = self.space.decoration_mode(surface, None);
+        decoration.mode(kde_mode(mode));
+    }
+
+    fn request_mode(
+        &mut self,
+        surface: &WlSurface,
+        decoration: &OrgKdeKwinServerDecoration,
+        mode: WEnum<KdeMode>,
+    ) {
+        let requested = match mode {
+            WEnum::Value(KdeMode::Server) => Some(DecorationMode::ServerSide),
+            WEnum::Value(KdeMode::Client) | WEnum::Value(KdeMode::None) => {
+                Some(DecorationMode::ClientSide)
+            }
+            _ => None,
+        };
+        let mode = self.space.decoration_mode(surface, requested);
+        decoration.mode(kde_mode(mode));
+    }
+}
+
+/// the kde decoration mode matching an xdg decoration mode
+fn kde_mode(mode: DecorationMode) -> KdeMode {
+    match mode {
+        DecorationMode::ClientSide => KdeMode::Client,
+        _ => KdeMode::Server,
+    }
+}
+
+delegate_xdg_decoration!(@<W: WrapperSpace + 'static> GlobalState<W>);
+delegate_kde_decoration!(@<W: WrapperSpace + 'static> GlobalState<W>);
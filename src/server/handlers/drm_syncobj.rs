@@ -0,0 +1,104 @@
+use std::{fs::OpenOptions, os::fd::OwnedFd};
+
+use smithay::{
+    backend::drm::DrmDeviceFd,
+    delegate_drm_syncobj,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, DisplayHandle, Resource},
+    utils::DeviceFd,
+    wayland::{
+        compositor::{add_blocker, add_pre_commit_hook, with_states, CompositorHandler},
+        drm_syncobj::{
+            supports_syncobj_eventfd, DrmSyncobjCachedState, DrmSyncobjHandler, DrmSyncobjState,
+        },
+    },
+};
+use tracing::{error, info, warn};
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// offer explicit sync to embedded clients
+    /// it is only offered when the host supports it too, so that the buffers of the host surfaces
+    /// which display embedded surfaces are synchronized explicitly by EGL as well
+    pub(crate) fn create_drm_syncobj_state(&mut self, dh: &DisplayHandle) {
+        if self.server_state.drm_syncobj_state.is_some() {
+            return;
+        }
+        if !self.client_state.host_drm_syncobj {
+            info!(
+                "The host doesn't support explicit sync, it won't be offered to embedded clients"
+            );
+            return;
+        }
+        let Some(path) = self.render_node().and_then(|node| node.dev_path()) else {
+            warn!("Failed to find the render node for explicit sync");
+            return;
+        };
+        let file = match OpenOptions::new().read(true).write(true).open(&path) {
+            Ok(file) => file,
+            Err(err) => {
+                error!(
+                    "Failed to open {} for explicit sync: {}",
+                    path.display(),
+                    err
+                );
+                return;
+            }
+        };
+        let device = DrmDeviceFd::new(DeviceFd::from(OwnedFd::from(file)));
+        if !supports_syncobj_eventfd(&device) {
+            info!("The render node doesn't support syncobj eventfds, explicit sync is disabled");
+            return;
+        }
+        self.server_state.drm_syncobj_state =
+            Some(DrmSyncobjState::new::<GlobalState<W>>(dh, device));
+    }
+}
+
+/// delay the commits of a surface until the acquire point of their buffer is signalled
+/// the buffers are then ready to be sampled by every render path, and their release points are
+/// signalled once the renderer drops them
+pub(crate) fn add_acquire_point_hook<W: WrapperSpace + 'static>(surface: &WlSurface) {
+    add_pre_commit_hook::<GlobalState<W>, _>(surface, |state, _dh, surface| {
+        let acquire_point = with_states(surface, |states| {
+            states
+                .cached_state
+                .get::<DrmSyncobjCachedState>()
+                .pending()
+                .acquire_point
+                .clone()
+        });
+        let Some(acquire_point) = acquire_point else {
+            return;
+        };
+        let Some(client) = surface.client() else {
+            return;
+        };
+        let (blocker, source) = match acquire_point.generate_blocker() {
+            Ok(res) => res,
+            Err(err) => {
+                warn!(?err, "Failed to wait on an acquire point");
+                return;
+            }
+        };
+        if let Err(err) = state.loop_handle.insert_source(source, move |_, _, state| {
+            let dh = state.server_state.display_handle.clone();
+            state
+                .client_compositor_state(&client)
+                .blocker_cleared(state, &dh);
+            Ok(())
+        }) {
+            error!("Failed to insert the acquire point source: {}", err);
+            return;
+        }
+        add_blocker(surface, blocker);
+    });
+}
+
+impl<W: WrapperSpace> DrmSyncobjHandler for GlobalState<W> {
+    fn drm_syncobj_state(&mut self) -> &mut DrmSyncobjState {
+        self.server_state.drm_syncobj_state.as_mut().unwrap()
+    }
+}
+
+delegate_drm_syncobj!(@<W: WrapperSpace + 'static> GlobalState<W>);
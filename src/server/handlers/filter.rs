@@ -0,0 +1,89 @@
+use smithay::{
+    reexports::{
+        wayland_protocols::wp::{
+            keyboard_shortcuts_inhibit::zv1::server::zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1,
+            primary_selection::zv1::server::zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1,
+        },
+        wayland_server::{
+            backend::GlobalId, protocol::wl_data_device_manager::WlDataDeviceManager, Client,
+            DataInit, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    wayland::{
+        keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
+        selection::{data_device::DataDeviceState, primary_selection::PrimarySelectionState},
+    },
+};
+
+use crate::{
+    client_state::{ClientTrust, WrapperClientCompositorState},
+    shared_state::GlobalState,
+    space::WrapperSpace,
+};
+
+/// global data of an embedded global which is only visible to sufficiently trusted clients
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GlobalFilter {
+    pub(crate) trust: ClientTrust,
+}
+
+impl GlobalFilter {
+    /// filter of globals which only trusted clients may bind
+    pub(crate) const TRUSTED: GlobalFilter = GlobalFilter {
+        trust: ClientTrust::Trusted,
+    };
+
+    pub(crate) fn can_view(&self, client: &Client) -> bool {
+        WrapperClientCompositorState::trust(client) >= self.trust
+    }
+
+    /// replace an unfiltered global created by smithay with a filtered one
+    /// must be called before any client is inserted, so that no client has bound the old global
+    pub(crate) fn replace_global<W: WrapperSpace + 'static, I>(
+        self,
+        dh: &DisplayHandle,
+        global: GlobalId,
+        version: u32,
+    ) where
+        I: Resource + 'static,
+        GlobalState<W>: GlobalDispatch<I, GlobalFilter>,
+    {
+        dh.remove_global::<GlobalState<W>>(global);
+        dh.create_global::<GlobalState<W>, I, _>(version, self);
+    }
+}
+
+/// forward the binding of a filtered global to the smithay state which handles the interface
+macro_rules! filtered_global {
+    ($interface:ty => $state:ty) => {
+        impl<W: WrapperSpace + 'static> GlobalDispatch<$interface, GlobalFilter>
+            for GlobalState<W>
+        {
+            fn bind(
+                state: &mut Self,
+                dh: &DisplayHandle,
+                client: &Client,
+                resource: New<$interface>,
+                _filter: &GlobalFilter,
+                data_init: &mut DataInit<'_, Self>,
+            ) {
+                <$state as GlobalDispatch<$interface, (), Self>>::bind(
+                    state,
+                    dh,
+                    client,
+                    resource,
+                    &(),
+                    data_init,
+                )
+            }
+
+            fn can_view(client: Client, filter: &GlobalFilter) -> bool {
+                filter.can_view(&client)
+            }
+        }
+    };
+}
+
+filtered_global!(WlDataDeviceManager => DataDeviceState);
+filtered_global!(ZwpPrimarySelectionDeviceManagerV1 => PrimarySelectionState);
+filtered_global!(ZwpKeyboardShortcutsInhibitManagerV1 => KeyboardShortcutsInhibitState);
@@ -0,0 +1,249 @@
+use smithay::{
+    input::pointer::{
+        AxisFrame, ButtonEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+        GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent,
+        GestureSwipeEndEvent, GestureSwipeUpdateEvent, GrabStartData, MotionEvent, PointerGrab,
+        PointerInnerHandle, RelativeMotionEvent,
+    },
+    reexports::{
+        wayland_protocols::xdg::shell::server::xdg_toplevel::{self, ResizeEdge},
+        wayland_server::protocol::wl_surface::WlSurface,
+    },
+    utils::{Logical, Point, Size},
+    wayland::shell::xdg::ToplevelSurface,
+};
+
+use crate::{
+    server::handlers::xdg_shell::constrain_size, shared_state::GlobalState, space::WrapperSpace,
+};
+
+/// forward the pointer events which don't affect a grab
+macro_rules! forward_pointer_events {
+    () => {
+        fn relative_motion(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            focus: Option<(WlSurface, Point<f64, Logical>)>,
+            event: &RelativeMotionEvent,
+        ) {
+            handle.relative_motion(data, focus, event);
+        }
+
+        fn axis(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            details: AxisFrame,
+        ) {
+            handle.axis(data, details)
+        }
+
+        fn frame(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+        ) {
+            handle.frame(data);
+        }
+
+        fn gesture_swipe_begin(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GestureSwipeBeginEvent,
+        ) {
+            handle.gesture_swipe_begin(data, event)
+        }
+
+        fn gesture_swipe_update(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GestureSwipeUpdateEvent,
+        ) {
+            handle.gesture_swipe_update(data, event)
+        }
+
+        fn gesture_swipe_end(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GestureSwipeEndEvent,
+        ) {
+            handle.gesture_swipe_end(data, event)
+        }
+
+        fn gesture_pinch_begin(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GesturePinchBeginEvent,
+        ) {
+            handle.gesture_pinch_begin(data, event)
+        }
+
+        fn gesture_pinch_update(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GesturePinchUpdateEvent,
+        ) {
+            handle.gesture_pinch_update(data, event)
+        }
+
+        fn gesture_pinch_end(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GesturePinchEndEvent,
+        ) {
+            handle.gesture_pinch_end(data, event)
+        }
+
+        fn gesture_hold_begin(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GestureHoldBeginEvent,
+        ) {
+            handle.gesture_hold_begin(data, event)
+        }
+
+        fn gesture_hold_end(
+            &mut self,
+            data: &mut GlobalState<W>,
+            handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+            event: &GestureHoldEndEvent,
+        ) {
+            handle.gesture_hold_end(data, event)
+        }
+
+        fn start_data(&self) -> &GrabStartData<GlobalState<W>> {
+            &self.start_data
+        }
+
+        fn unset(&mut self, _data: &mut GlobalState<W>) {}
+    };
+}
+
+/// interactive move of an embedded toplevel
+pub(crate) struct MoveGrab<W: WrapperSpace + 'static> {
+    pub(crate) start_data: GrabStartData<GlobalState<W>>,
+    pub(crate) toplevel: ToplevelSurface,
+}
+
+impl<W: WrapperSpace + 'static> PointerGrab<GlobalState<W>> for MoveGrab<W> {
+    fn motion(
+        &mut self,
+        data: &mut GlobalState<W>,
+        handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // the pointer has no focus while the toplevel is moved
+        handle.motion(data, None, event);
+        let offset = event.location - self.start_data.location;
+        data.space
+            .toplevel_move(&self.toplevel, offset.to_i32_round());
+    }
+
+    fn button(
+        &mut self,
+        data: &mut GlobalState<W>,
+        handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+            data.space.toplevel_grab_ended(&self.toplevel);
+        }
+    }
+
+    forward_pointer_events!();
+}
+
+/// interactive resize of an embedded toplevel
+pub(crate) struct ResizeGrab<W: WrapperSpace + 'static> {
+    pub(crate) start_data: GrabStartData<GlobalState<W>>,
+    pub(crate) toplevel: ToplevelSurface,
+    pub(crate) edges: ResizeEdge,
+    pub(crate) initial_size: Size<i32, Logical>,
+    pub(crate) last_size: Size<i32, Logical>,
+}
+
+impl<W: WrapperSpace + 'static> PointerGrab<GlobalState<W>> for ResizeGrab<W> {
+    fn motion(
+        &mut self,
+        data: &mut GlobalState<W>,
+        handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+        _focus: Option<(WlSurface, Point<f64, Logical>)>,
+        event: &MotionEvent,
+    ) {
+        // the pointer has no focus while the toplevel is resized
+        handle.motion(data, None, event);
+        if !self.toplevel.alive() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+            return;
+        }
+
+        let delta = (event.location - self.start_data.location).to_i32_round::<i32>();
+        let mut size = self.initial_size;
+        if matches!(
+            self.edges,
+            ResizeEdge::Left | ResizeEdge::TopLeft | ResizeEdge::BottomLeft
+        ) {
+            size.w -= delta.x;
+        } else if matches!(
+            self.edges,
+            ResizeEdge::Right | ResizeEdge::TopRight | ResizeEdge::BottomRight
+        ) {
+            size.w += delta.x;
+        }
+        if matches!(
+            self.edges,
+            ResizeEdge::Top | ResizeEdge::TopLeft | ResizeEdge::TopRight
+        ) {
+            size.h -= delta.y;
+        } else if matches!(
+            self.edges,
+            ResizeEdge::Bottom | ResizeEdge::BottomLeft | ResizeEdge::BottomRight
+        ) {
+            size.h += delta.y;
+        }
+        let size = constrain_size(self.toplevel.wl_surface(), size);
+        if size == self.last_size {
+            return;
+        }
+        self.last_size = size;
+
+        self.toplevel.with_pending_state(|state| {
+            state.states.set(xdg_toplevel::State::Resizing);
+            state.size = Some(size);
+        });
+        self.toplevel.send_configure();
+        data.space.toplevel_resize(&self.toplevel, self.edges, size);
+    }
+
+    fn button(
+        &mut self,
+        data: &mut GlobalState<W>,
+        handle: &mut PointerInnerHandle<'_, GlobalState<W>>,
+        event: &ButtonEvent,
+    ) {
+        handle.button(data, event);
+        if handle.current_pressed().is_empty() {
+            handle.unset_grab(self, data, event.serial, event.time, true);
+            if self.toplevel.alive() {
+                self.toplevel.with_pending_state(|state| {
+                    state.states.unset(xdg_toplevel::State::Resizing);
+                });
+                self.toplevel.send_configure();
+            }
+            data.space.toplevel_grab_ended(&self.toplevel);
+        }
+    }
+
+    forward_pointer_events!();
+}
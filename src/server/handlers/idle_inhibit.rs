@@ -0,0 +1,102 @@
+use smithay::{
+    delegate_idle_inhibit,
+    desktop::utils::bbox_from_surface_tree,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface, Resource},
+    wayland::idle_inhibit::IdleInhibitHandler,
+};
+
+use crate::{
+    shared_state::GlobalState,
+    space::{Visibility, WrapperSpace},
+};
+
+impl<W: WrapperSpace> IdleInhibitHandler for GlobalState<W> {
+    fn inhibit(&mut self, surface: WlSurface) {
+        // the host inhibitor is created once the surface is visible
+        let Some(client) = surface.client() else {
+            return;
+        };
+        if !self
+            .server_state
+            .idle_inhibiting_surfaces
+            .iter()
+            .any(|(s, _)| s == &surface)
+        {
+            self.server_state
+                .idle_inhibiting_surfaces
+                .push((surface, client.id()));
+        }
+    }
+
+    fn uninhibit(&mut self, surface: WlSurface) {
+        self.server_state
+            .idle_inhibiting_surfaces
+            .retain(|(s, _)| s != &surface);
+    }
+}
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// keep a host idle inhibitor on the host surface of each embedded surface which inhibits
+    /// idling, while the embedded surface is mapped and visible
+    pub(crate) fn update_idle_inhibitors(&mut self) {
+        self.server_state
+            .idle_inhibiting_surfaces
+            .retain(|(s, _)| s.is_alive());
+        if self.server_state.idle_inhibiting_surfaces.is_empty()
+            && self.client_state.idle_inhibitors.is_empty()
+        {
+            return;
+        }
+
+        let hidden = matches!(self.space.visibility(), Visibility::Hidden);
+        let visible: Vec<_> = self
+            .server_state
+            .idle_inhibiting_surfaces
+            .iter()
+            .map(|(s, _)| s)
+            .filter(|_| !hidden)
+            // surfaces without a buffer are unmapped
+            .filter(|s_surface| {
+                let size = bbox_from_surface_tree(s_surface, (0, 0)).size;
+                size.w > 0 && size.h > 0
+            })
+            .filter_map(|s_surface| {
+                let c_surface = self.host_surface_of(s_surface)?;
+                (!self.client_state.visible_outputs(&c_surface).is_empty())
+                    .then(|| (s_surface.clone(), c_surface))
+            })
+            .collect();
+
+        self.client_state
+            .idle_inhibitors
+            .retain(|(s_surface, c_surface, c_inhibitor)| {
+                let keep = visible
+                    .iter()
+                    .any(|(s, c)| s == s_surface && c == c_surface);
+                if !keep {
+                    c_inhibitor.destroy();
+                }
+                keep
+            });
+
+        let Some(manager) = self.client_state.idle_inhibit_manager.as_ref() else {
+            return;
+        };
+        for (s_surface, c_surface) in visible {
+            if self
+                .client_state
+                .idle_inhibitors
+                .iter()
+                .any(|(s, ..)| s == &s_surface)
+            {
+                continue;
+            }
+            let c_inhibitor = manager.create_inhibitor(&c_surface, &self.client_state.queue_handle);
+            self.client_state
+                .idle_inhibitors
+                .push((s_surface, c_surface, c_inhibitor));
+        }
+    }
+}
+
+delegate_idle_inhibit!(@<W: WrapperSpace + 'static> GlobalState<W>);
@@ -0,0 +1,111 @@
+use sctk::reexports::client::protocol::wl_surface::WlSurface;
+use smithay::{
+    delegate_keyboard_shortcuts_inhibit,
+    wayland::keyboard_shortcuts_inhibit::{
+        KeyboardShortcutsInhibitHandler, KeyboardShortcutsInhibitState, KeyboardShortcutsInhibitor,
+        KeyboardShortcutsInhibitorSeat,
+    },
+};
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+impl<W: WrapperSpace> KeyboardShortcutsInhibitHandler for GlobalState<W> {
+    fn keyboard_shortcuts_inhibit_state(&mut self) -> &mut KeyboardShortcutsInhibitState {
+        &mut self.server_state.keyboard_shortcuts_inhibit_state
+    }
+
+    fn new_inhibitor(&mut self, _inhibitor: KeyboardShortcutsInhibitor) {
+        // the inhibitor is forwarded to the host once its surface is focused
+        self.update_shortcuts_inhibitors();
+    }
+
+    fn inhibitor_destroyed(&mut self, _inhibitor: KeyboardShortcutsInhibitor) {
+        self.update_shortcuts_inhibitors();
+    }
+}
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// forward the inhibitor of the focused embedded surface of each seat to the host
+    pub(crate) fn update_shortcuts_inhibitors(&mut self) {
+        let seat_names: Vec<_> = self
+            .server_state
+            .seats
+            .iter()
+            .map(|s| s.name.clone())
+            .collect();
+        for seat_name in seat_names {
+            self.update_shortcuts_inhibitor(&seat_name);
+        }
+    }
+
+    /// forward the inhibitor of the focused embedded surface of a seat to the host surface which
+    /// displays it, and destroy the host inhibitor of a surface which lost focus
+    pub(crate) fn update_shortcuts_inhibitor(&mut self, seat_name: &str) {
+        let inhibitor = self.focus_manager.focused(seat_name).and_then(|s_surface| {
+            let seat = self
+                .server_state
+                .seats
+                .iter()
+                .find(|s| s.name == seat_name)?;
+            let inhibitor = seat
+                .server
+                .seat
+                .keyboard_shortcuts_inhibitor_for_surface(s_surface)?;
+            Some((inhibitor, self.host_surface_of(s_surface)?))
+        });
+
+        let inhibitors = &mut self.client_state.shortcuts_inhibitors;
+        if let Some(i) = inhibitors.iter().position(|(name, ..)| name == seat_name) {
+            let (_, old, c_surface, _) = &inhibitors[i];
+            if inhibitor
+                .as_ref()
+                .is_some_and(|(inhibitor, c)| inhibitor == old && c == c_surface)
+            {
+                return;
+            }
+            let (_, old, _, c_inhibitor) = inhibitors.remove(i);
+            c_inhibitor.destroy();
+            old.inactivate();
+        }
+
+        let Some((inhibitor, c_surface)) = inhibitor else {
+            return;
+        };
+        let (Some(manager), Some(seat)) = (
+            self.client_state
+                .keyboard_shortcuts_inhibit_manager
+                .as_ref(),
+            self.server_state.seats.iter().find(|s| s.name == seat_name),
+        ) else {
+            return;
+        };
+        let c_inhibitor = manager.inhibit_shortcuts(
+            &c_surface,
+            &seat.client.seat,
+            inhibitor.clone(),
+            &self.client_state.queue_handle,
+        );
+        self.client_state.shortcuts_inhibitors.push((
+            seat_name.to_string(),
+            inhibitor,
+            c_surface,
+            c_inhibitor,
+        ));
+    }
+
+    /// destroy the host inhibitors of a host surface which is being destroyed
+    pub(crate) fn drop_shortcuts_inhibitors(&mut self, c_surface: &WlSurface) {
+        self.client_state
+            .shortcuts_inhibitors
+            .retain(|(_, inhibitor, c, c_inhibitor)| {
+                if c != c_surface {
+                    return true;
+                }
+                c_inhibitor.destroy();
+                inhibitor.inactivate();
+                false
+            });
+    }
+}
+
+delegate_keyboard_shortcuts_inhibit!(@<W: WrapperSpace + 'static> GlobalState<W>);
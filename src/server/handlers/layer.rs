@@ -1,3 +1,4 @@
+use sctk::shell::WaylandSurface;
 use smithay::{
     delegate_layer_shell,
     wayland::shell::wlr_layer::{Layer, WlrLayerShellHandler},
@@ -24,9 +25,20 @@ impl<W: WrapperSpace> WlrLayerShellHandler for GlobalState<W> {
     }
 
     fn layer_destroyed(&mut self, surface: smithay::wayland::shell::wlr_layer::LayerSurface) {
-        // cleanup proxied surfaces
+        // a layer surface may be destroyed before its first commit
         self.client_state
+            .pending_layer_surfaces
+            .retain(|(s, ..)| s != &surface);
+        // cleanup proxied surfaces
+        let Some(i) = self
+            .client_state
             .proxied_layer_surfaces
-            .retain(|s| s.2.wl_surface() != surface.wl_surface());
+            .iter()
+            .position(|s| s.2.wl_surface() == surface.wl_surface())
+        else {
+            return;
+        };
+        let (_, _, _, c_layer, ..) = self.client_state.proxied_layer_surfaces.remove(i);
+        self.host_surface_destroyed(c_layer.wl_surface());
     }
 }
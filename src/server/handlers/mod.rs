@@ -1,4 +1,4 @@
-use std::{cell::RefMut, os::fd::OwnedFd, rc::Rc, sync::Mutex};
+use std::{os::fd::OwnedFd, rc::Rc, sync::Mutex};
 
 use itertools::Itertools;
 use sctk::{
@@ -46,11 +46,29 @@ use crate::{
 };
 
 pub(crate) mod compositor;
+pub(crate) mod decoration;
+pub(crate) mod drm_syncobj;
+pub(crate) mod filter;
 pub(crate) mod fractional;
+pub(crate) mod grabs;
+pub(crate) mod idle_inhibit;
+pub(crate) mod keyboard_shortcuts_inhibit;
 pub(crate) mod layer;
+pub(crate) mod presentation;
 pub(crate) mod viewporter;
+pub(crate) mod xdg_activation;
 pub(crate) mod xdg_shell;
 
+/// the owner of a selection set by the wrapper on an embedded seat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SelectionOrigin {
+    /// the selection is offered by the host
+    Host,
+    /// the selection is owned by an X11 client
+    #[cfg(feature = "xwayland")]
+    Xwayland,
+}
+
 impl<W: WrapperSpace> PrimarySelectionHandler for GlobalState<W> {
     fn primary_selection_state(&self) -> &PrimarySelectionState {
         &self.server_state.primary_selection_state
@@ -134,8 +152,8 @@ impl<W: WrapperSpace> SeatHandler for GlobalState<W> {
                 let last_enter = seat_pair.client.last_enter;
 
                 let _ = with_states(&surface, |data| {
-                    let surface_attributes = data.cached_state.current::<SurfaceAttributes>();
-                    let buf = RefMut::map(surface_attributes, |s| &mut s.buffer);
+                    let mut surface_attributes = data.cached_state.get::<SurfaceAttributes>();
+                    let buf = &surface_attributes.current().buffer;
                     if let Some(hotspot) = data
                         .data_map
                         .get::<Mutex<CursorImageAttributes>>()
@@ -269,6 +287,11 @@ impl<W: WrapperSpace> ClientDndGrabHandler for GlobalState<W> {
             seat.client.dnd_source = Some(dnd_source);
         }
 
+        seat.server.dnd_client = source
+            .as_ref()
+            .and_then(|s| s.client())
+            .or_else(|| icon.as_ref().and_then(|i| i.client()))
+            .map(|c| c.id());
         seat.server.dnd_source = source;
         seat.server.dnd_icon = icon;
     }
@@ -286,6 +309,7 @@ impl<W: WrapperSpace> ClientDndGrabHandler for GlobalState<W> {
         // XXX is this correct?
         seat.server.dnd_source = None;
         seat.server.dnd_icon = None;
+        seat.server.dnd_client = None;
         seat.client.dnd_icon = None;
         seat.client.dnd_source = None;
     }
@@ -384,20 +408,30 @@ impl<W: WrapperSpace> DmabufHandler for GlobalState<W> {
         &mut self,
         _global: &smithay::wayland::dmabuf::DmabufGlobal,
         dmabuf: smithay::backend::allocator::dmabuf::Dmabuf,
-        _: ImportNotifier,
+        notifier: ImportNotifier,
     ) {
-        if let Some(Err(err)) = self
+        match self
             .space
             .renderer()
             .map(|renderer| renderer.import_dmabuf(&dmabuf, None))
         {
-            error!("Failed to import dmabuf: {}", err);
+            Some(Ok(_)) => {
+                let _ = notifier.successful::<GlobalState<W>>();
+            }
+            Some(Err(err)) => {
+                error!("Failed to import dmabuf: {}", err);
+                notifier.failed();
+            }
+            None => {
+                error!("Failed to import dmabuf: no renderer");
+                notifier.failed();
+            }
         }
     }
 }
 
 impl<W: WrapperSpace> SelectionHandler for GlobalState<W> {
-    type SelectionUserData = ();
+    type SelectionUserData = SelectionOrigin;
 
     fn new_selection(
         &mut self,
@@ -405,6 +439,9 @@ impl<W: WrapperSpace> SelectionHandler for GlobalState<W> {
         source: Option<SelectionSource>,
         seat: Seat<GlobalState<W>>,
     ) {
+        #[cfg(feature = "xwayland")]
+        self.x11_new_selection(target, source.as_ref().map(|s| s.mime_types()));
+
         let seat = match self
             .server_state
             .seats
@@ -436,8 +473,16 @@ impl<W: WrapperSpace> SelectionHandler for GlobalState<W> {
         mime_type: String,
         fd: OwnedFd,
         seat: Seat<Self>,
-        _: &Self::SelectionUserData,
+        origin: &Self::SelectionUserData,
     ) {
+        #[cfg(feature = "xwayland")]
+        if *origin == SelectionOrigin::Xwayland {
+            self.x11_send_selection(target, mime_type, fd);
+            return;
+        }
+        #[cfg(not(feature = "xwayland"))]
+        let _ = (target, origin);
+
         let seat = match self
             .server_state
             .seats
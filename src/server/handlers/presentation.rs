@@ -0,0 +1,5 @@
+use smithay::delegate_presentation;
+
+use crate::{shared_state::GlobalState, space::WrapperSpace};
+
+delegate_presentation!(@<W: WrapperSpace + 'static> GlobalState<W>);
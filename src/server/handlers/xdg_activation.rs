@@ -0,0 +1,174 @@
+use std::sync::Mutex;
+
+use sctk::activation::RequestData;
+use smithay::{
+    input::Seat,
+    reexports::{
+        wayland_protocols::xdg::activation::v1::server::{
+            xdg_activation_token_v1::{self, XdgActivationTokenV1},
+            xdg_activation_v1::{self, XdgActivationV1},
+        },
+        wayland_server::{
+            protocol::{wl_seat::WlSeat, wl_surface::WlSurface},
+            Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+        },
+    },
+    utils::SERIAL_COUNTER,
+};
+use tracing::warn;
+
+use crate::{
+    client::handlers::activation::EmbeddedTokenRequest, server::handlers::filter::GlobalFilter,
+    shared_state::GlobalState, space::WrapperSpace,
+};
+
+/// state of an embedded activation token, until it is committed
+#[derive(Debug, Default)]
+pub(crate) struct EmbeddedToken {
+    app_id: Option<String>,
+    seat: Option<WlSeat>,
+    surface: Option<WlSurface>,
+    committed: bool,
+}
+
+impl<W: WrapperSpace + 'static> GlobalDispatch<XdgActivationV1, GlobalFilter> for GlobalState<W> {
+    fn bind(
+        _state: &mut Self,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<XdgActivationV1>,
+        _global_data: &GlobalFilter,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+
+    fn can_view(client: Client, filter: &GlobalFilter) -> bool {
+        filter.can_view(&client)
+    }
+}
+
+impl<W: WrapperSpace + 'static> Dispatch<XdgActivationV1, ()> for GlobalState<W> {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &XdgActivationV1,
+        request: xdg_activation_v1::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        match request {
+            xdg_activation_v1::Request::GetActivationToken { id } => {
+                data_init.init(id, Mutex::new(EmbeddedToken::default()));
+            }
+            xdg_activation_v1::Request::Activate { token, surface } => {
+                state.activation_requested(token, &surface);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<W: WrapperSpace + 'static> Dispatch<XdgActivationTokenV1, Mutex<EmbeddedToken>>
+    for GlobalState<W>
+{
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &XdgActivationTokenV1,
+        request: xdg_activation_token_v1::Request,
+        data: &Mutex<EmbeddedToken>,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        let mut data = data.lock().unwrap();
+        if data.committed && !matches!(request, xdg_activation_token_v1::Request::Destroy) {
+            resource.post_error(
+                xdg_activation_token_v1::Error::AlreadyUsed,
+                "The activation token has already been committed",
+            );
+            return;
+        }
+        match request {
+            // embedded serials mean nothing to the host, so they only identify the seat
+            xdg_activation_token_v1::Request::SetSerial { seat, .. } => data.seat = Some(seat),
+            xdg_activation_token_v1::Request::SetAppId { app_id } => data.app_id = Some(app_id),
+            xdg_activation_token_v1::Request::SetSurface { surface } => {
+                data.surface = Some(surface)
+            }
+            xdg_activation_token_v1::Request::Commit => {
+                data.committed = true;
+                let token = EmbeddedToken {
+                    app_id: data.app_id.take(),
+                    seat: data.seat.take(),
+                    surface: data.surface.take(),
+                    committed: true,
+                };
+                drop(data);
+                state.request_activation_token(resource, token);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// request a host activation token for a committed embedded token
+    /// the host token is requested with the serial of the last input event of the host seat, on
+    /// the host surface which displays the embedded surface
+    fn request_activation_token(&mut self, resource: &XdgActivationTokenV1, token: EmbeddedToken) {
+        let seat_and_serial = token.seat.as_ref().and_then(|seat| {
+            let seat = Seat::<Self>::from_resource(seat)?;
+            self.server_state
+                .seats
+                .iter()
+                .find(|s| s.server.seat == seat)
+                .map(|s| {
+                    (
+                        s.client.seat.clone(),
+                        s.client.get_serial_of_last_seat_event(),
+                    )
+                })
+        });
+        let surface = token
+            .surface
+            .as_ref()
+            .and_then(|s_surface| self.host_surface_of(s_surface));
+
+        let Some(activation_state) = self.client_state.activation_state.as_ref() else {
+            // the client must receive a token, even if it can't be used to activate anything
+            warn!("The host doesn't support xdg activation, the embedded token won't activate");
+            resource.done(format!(
+                "xdg-shell-wrapper-{}",
+                u32::from(SERIAL_COUNTER.next_serial())
+            ));
+            return;
+        };
+        activation_state.request_token_with_data(
+            &self.client_state.queue_handle,
+            EmbeddedTokenRequest {
+                data: RequestData {
+                    app_id: token.app_id,
+                    seat_and_serial,
+                    surface,
+                },
+                token: resource.clone(),
+            },
+        );
+    }
+
+    /// an embedded client requested activation of a surface
+    /// the host surface which displays it is activated with the same token if the space allows it
+    fn activation_requested(&mut self, token: String, s_surface: &WlSurface) {
+        if !self.space.activation_requested(&token, s_surface) {
+            return;
+        }
+        if let (Some(activation_state), Some(c_surface)) = (
+            self.client_state.activation_state.as_ref(),
+            self.host_surface_of(s_surface),
+        ) {
+            activation_state.activate::<Self>(&c_surface, token);
+        }
+    }
+}
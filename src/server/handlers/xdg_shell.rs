@@ -1,17 +1,32 @@
 use sctk::shell::xdg::XdgPositioner;
 use smithay::{
     delegate_xdg_shell,
-    desktop::{PopupKind, Window},
+    desktop::{
+        find_popup_root_surface, utils::bbox_from_surface_tree, PopupKind, PopupManager, Window,
+    },
+    input::{pointer::Focus, Seat},
     reexports::{
-        wayland_protocols::xdg::shell::server::xdg_toplevel, wayland_server::protocol::wl_seat,
+        wayland_protocols::xdg::shell::server::xdg_toplevel,
+        wayland_server::{
+            protocol::{wl_output, wl_seat, wl_surface::WlSurface},
+            Resource,
+        },
     },
-    utils::Serial,
-    wayland::shell::xdg::{
-        PopupSurface, PositionerState, ToplevelSurface, XdgShellHandler, XdgShellState,
+    utils::{Logical, Serial, Size},
+    wayland::{
+        compositor::with_states,
+        shell::xdg::{
+            PopupSurface, PositionerState, SurfaceCachedState, ToplevelSurface, XdgShellHandler,
+            XdgShellState,
+        },
     },
 };
 
-use crate::{shared_state::GlobalState, space::WrapperSpace};
+use crate::{
+    server::handlers::grabs::{MoveGrab, ResizeGrab},
+    shared_state::GlobalState,
+    space::{ToplevelRequest, WrapperSpace},
+};
 
 impl<W: WrapperSpace> XdgShellHandler for GlobalState<W> {
     fn xdg_shell_state(&mut self) -> &mut XdgShellState {
@@ -19,9 +34,10 @@ impl<W: WrapperSpace> XdgShellHandler for GlobalState<W> {
     }
 
     fn new_toplevel(&mut self, surface: ToplevelSurface) {
-        let window = Window::new(surface.clone());
+        let window = Window::new_wayland_window(surface.clone());
 
         self.space.add_window(window);
+        apply_size_constraints(&surface);
         surface.send_configure();
     }
 
@@ -54,16 +70,78 @@ impl<W: WrapperSpace> XdgShellHandler for GlobalState<W> {
         }
     }
 
-    fn move_request(&mut self, _surface: ToplevelSurface, _seat: wl_seat::WlSeat, _serial: Serial) {
+    fn move_request(&mut self, surface: ToplevelSurface, seat: wl_seat::WlSeat, serial: Serial) {
+        let Some((seat_name, seat)) = self.grab_seat(&surface, &seat, serial) else {
+            return;
+        };
+        if !self.space.toplevel_move_request(&seat_name, &surface) {
+            return;
+        }
+        let pointer = seat.get_pointer().unwrap();
+        let grab = MoveGrab {
+            start_data: pointer.grab_start_data().unwrap(),
+            toplevel: surface,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
     }
 
     fn resize_request(
         &mut self,
-        _surface: ToplevelSurface,
-        _seat: wl_seat::WlSeat,
-        _serial: Serial,
-        _edges: xdg_toplevel::ResizeEdge,
+        surface: ToplevelSurface,
+        seat: wl_seat::WlSeat,
+        serial: Serial,
+        edges: xdg_toplevel::ResizeEdge,
     ) {
+        let Some((seat_name, seat)) = self.grab_seat(&surface, &seat, serial) else {
+            return;
+        };
+        if !self
+            .space
+            .toplevel_resize_request(&seat_name, &surface, edges)
+        {
+            return;
+        }
+        let initial_size = surface
+            .current_state()
+            .size
+            .unwrap_or_else(|| bbox_from_surface_tree(surface.wl_surface(), (0, 0)).size);
+        let pointer = seat.get_pointer().unwrap();
+        let grab = ResizeGrab {
+            start_data: pointer.grab_start_data().unwrap(),
+            toplevel: surface,
+            edges,
+            initial_size,
+            last_size: initial_size,
+        };
+        pointer.set_grab(self, grab, serial, Focus::Clear);
+    }
+
+    fn maximize_request(&mut self, surface: ToplevelSurface) {
+        self.toplevel_state_request(surface, ToplevelRequest::Maximize);
+    }
+
+    fn unmaximize_request(&mut self, surface: ToplevelSurface) {
+        self.toplevel_state_request(surface, ToplevelRequest::Unmaximize);
+    }
+
+    fn fullscreen_request(
+        &mut self,
+        surface: ToplevelSurface,
+        output: Option<wl_output::WlOutput>,
+    ) {
+        self.toplevel_state_request(surface, ToplevelRequest::Fullscreen(output));
+    }
+
+    fn unfullscreen_request(&mut self, surface: ToplevelSurface) {
+        self.toplevel_state_request(surface, ToplevelRequest::Unfullscreen);
+    }
+
+    fn minimize_request(&mut self, surface: ToplevelSurface) {
+        self.toplevel_state_request(surface, ToplevelRequest::Minimize);
+    }
+
+    fn parent_changed(&mut self, surface: ToplevelSurface) {
+        self.space.toplevel_parent_changed(&surface);
     }
 
     fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {
@@ -97,9 +175,124 @@ impl<W: WrapperSpace> XdgShellHandler for GlobalState<W> {
     }
 
     fn popup_destroyed(&mut self, surface: PopupSurface) {
-        self.server_state.popup_manager.commit(surface.wl_surface());
+        // child popups should be destroyed first, but a disconnecting client may destroy them in
+        // any order, so the whole host popup chain is torn down, deepest child first, as the host
+        // doesn't allow destroying a popup which isn't the topmost one
+        let mut popups = child_popups(&self.server_state.popup_manager, &surface);
+        popups.push(surface);
+        self.space.popup_destroyed(&popups);
+        self.server_state.popup_manager.cleanup();
     }
 }
 
+impl<W: WrapperSpace> GlobalState<W> {
+    /// the embedded seat which an interactive grab of a toplevel may be started on
+    /// the serial must be the one of a button press which is still held on one of the surfaces of
+    /// the toplevel's client
+    fn grab_seat(
+        &self,
+        surface: &ToplevelSurface,
+        seat: &wl_seat::WlSeat,
+        serial: Serial,
+    ) -> Option<(String, Seat<Self>)> {
+        let seat = Seat::<Self>::from_resource(seat)?;
+        let pointer = seat.get_pointer()?;
+        if !pointer.has_grab(serial) {
+            return None;
+        }
+        let start_data = pointer.grab_start_data()?;
+        let (focus, _) = start_data.focus.as_ref()?;
+        if !focus.id().same_client_as(&surface.wl_surface().id()) {
+            return None;
+        }
+        self.server_state
+            .seats
+            .iter()
+            .find(|s| s.server.seat == seat)
+            .map(|s| (s.name.clone(), seat))
+    }
+
+    /// route a state change request of a toplevel to the space, then configure the toplevel
+    /// clients expect a configure in response, even if the state didn't change
+    fn toplevel_state_request(&mut self, surface: ToplevelSurface, request: ToplevelRequest) {
+        self.space.toplevel_state_request(&surface, request);
+        apply_size_constraints(&surface);
+        if surface.is_initial_configure_sent() {
+            surface.send_configure();
+        }
+    }
+}
+
+/// clamp a size to the minimum and maximum size of an embedded toplevel
+/// a bound of 0 means that the size is unconstrained in that direction
+pub(crate) fn constrain_size(surface: &WlSurface, size: Size<i32, Logical>) -> Size<i32, Logical> {
+    let (min_size, max_size) = with_states(surface, |states| {
+        let mut state = states.cached_state.get::<SurfaceCachedState>();
+        let state = state.current();
+        (state.min_size, state.max_size)
+    });
+    let clamp = |value: i32, min: i32, max: i32| {
+        let value = if max > 0 { value.min(max) } else { value };
+        value.max(min).max(1)
+    };
+    (
+        clamp(size.w, min_size.w, max_size.w),
+        clamp(size.h, min_size.h, max_size.h),
+    )
+        .into()
+}
+
+/// clamp the pending size of an embedded toplevel to its size constraints
+/// dimensions of 0 are left to the client
+/// returns whether the pending size changed
+pub(crate) fn apply_size_constraints(surface: &ToplevelSurface) -> bool {
+    let Some(size) = surface.with_pending_state(|state| state.size) else {
+        return false;
+    };
+    let mut constrained = constrain_size(surface.wl_surface(), size);
+    if size.w == 0 {
+        constrained.w = 0;
+    }
+    if size.h == 0 {
+        constrained.h = 0;
+    }
+    if constrained == size {
+        return false;
+    }
+    surface.with_pending_state(|state| state.size = Some(constrained));
+    true
+}
+
+/// popups which descend from an embedded popup, deepest child first
+fn child_popups(popup_manager: &PopupManager, popup: &PopupSurface) -> Vec<PopupSurface> {
+    let Ok(root) = find_popup_root_surface(&PopupKind::Xdg(popup.clone())) else {
+        return Vec::new();
+    };
+    let mut children: Vec<_> = PopupManager::popups_for_surface(&root)
+        .filter_map(|(p, _)| match p {
+            PopupKind::Xdg(p) => Some(p),
+            _ => None,
+        })
+        .filter_map(|p| {
+            // the depth of the child below the popup
+            let mut depth = 1;
+            let mut parent = p.get_parent_surface();
+            while let Some(surface) = parent {
+                if &surface == popup.wl_surface() {
+                    return Some((depth, p));
+                }
+                depth += 1;
+                parent = match popup_manager.find_popup(&surface) {
+                    Some(PopupKind::Xdg(p)) => p.get_parent_surface(),
+                    _ => None,
+                };
+            }
+            None
+        })
+        .collect();
+    children.sort_by(|(a, _), (b, _)| b.cmp(a));
+    children.into_iter().map(|(_, p)| p).collect()
+}
+
 // Xdg Shell
 delegate_xdg_shell!(@<W: WrapperSpace + 'static> GlobalState<W>);
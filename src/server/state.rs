@@ -1,23 +1,53 @@
 use smithay::{
     desktop::PopupManager,
     input::{Seat, SeatState},
-    reexports::wayland_server::{
-        protocol::{wl_data_source::WlDataSource, wl_surface::WlSurface},
-        DisplayHandle,
+    reexports::{
+        wayland_protocols::{
+            wp::{
+                keyboard_shortcuts_inhibit::zv1::server::zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1,
+                primary_selection::zv1::server::zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1,
+            },
+            xdg::activation::v1::server::xdg_activation_v1::XdgActivationV1,
+        },
+        wayland_protocols_misc::server_decoration::server::org_kde_kwin_server_decoration::Mode as KdeMode,
+        wayland_server::{
+            backend::{ClientId, GlobalId},
+            protocol::{
+                wl_data_device_manager::WlDataDeviceManager, wl_data_source::WlDataSource,
+                wl_surface::WlSurface,
+            },
+            DisplayHandle,
+        },
     },
     utils::{Logical, Point},
     wayland::{
         compositor::CompositorState,
         dmabuf::{DmabufGlobal, DmabufState},
+        drm_syncobj::DrmSyncobjState,
         fractional_scale::FractionalScaleManagerState,
+        idle_inhibit::IdleInhibitManagerState,
+        keyboard_shortcuts_inhibit::KeyboardShortcutsInhibitState,
         output::OutputManagerState,
+        presentation::PresentationState,
         selection::{data_device::DataDeviceState, primary_selection::PrimarySelectionState},
-        shell::{wlr_layer::WlrLayerShellState, xdg::XdgShellState},
+        shell::{
+            kde::decoration::KdeDecorationState,
+            wlr_layer::WlrLayerShellState,
+            xdg::{decoration::XdgDecorationState, XdgShellState},
+        },
         shm::ShmState,
     },
 };
 
-use crate::{client_state::ClientSeat, shared_state::GlobalState, space::WrapperSpace};
+#[cfg(feature = "xwayland")]
+use smithay::wayland::xwayland_shell::XWaylandShellState;
+
+use crate::{
+    client_state::ClientSeat,
+    server::handlers::filter::GlobalFilter,
+    shared_state::{DmabufFeedbackParams, GlobalState},
+    space::WrapperSpace,
+};
 
 /// list of focused surfaces and the seats that focus them
 
@@ -52,19 +82,58 @@ pub struct ServerState<W: WrapperSpace + 'static> {
     // Smithay State
     pub(crate) compositor_state: CompositorState,
     pub(crate) xdg_shell_state: XdgShellState,
+    pub(crate) _xdg_decoration_state: XdgDecorationState,
+    pub(crate) kde_decoration_state: KdeDecorationState,
     pub(crate) shm_state: ShmState,
     pub(crate) _output_manager_state: OutputManagerState,
     pub(crate) seat_state: SeatState<GlobalState<W>>,
     pub(crate) data_device_state: DataDeviceState,
     pub(crate) dmabuf_state: Option<(DmabufState, DmabufGlobal)>,
+    /// the parameters the current default dmabuf feedback was built from
+    pub(crate) dmabuf_feedback_params: Option<DmabufFeedbackParams>,
+    /// created when the display is bound, if both the renderer and the host support explicit sync
+    pub(crate) drm_syncobj_state: Option<DrmSyncobjState>,
     pub(crate) primary_selection_state: PrimarySelectionState,
     pub(crate) layer_shell_state: WlrLayerShellState,
     pub(crate) _fractional_scale_state: FractionalScaleManagerState,
+    pub(crate) keyboard_shortcuts_inhibit_state: KeyboardShortcutsInhibitState,
+    pub(crate) _idle_inhibit_state: IdleInhibitManagerState,
+    pub(crate) _xdg_activation_global: GlobalId,
+    /// embedded surfaces which inhibit idling, and their clients
+    pub(crate) idle_inhibiting_surfaces: Vec<(WlSurface, ClientId)>,
+    /// created once the host has announced its presentation clock
+    pub(crate) presentation_state: Option<PresentationState>,
+    /// associates the surfaces of Xwayland with their X11 windows
+    #[cfg(feature = "xwayland")]
+    pub(crate) xwayland_shell_state: XWaylandShellState,
 }
 
 impl<W: WrapperSpace> ServerState<W> {
     /// create a new server state
     pub fn new(dh: DisplayHandle) -> ServerState<W> {
+        // the selection globals let clients read the clipboard, so they are only visible to
+        // trusted clients
+        let data_device_state = DataDeviceState::new::<GlobalState<W>>(&dh);
+        GlobalFilter::TRUSTED.replace_global::<W, WlDataDeviceManager>(
+            &dh,
+            data_device_state.global(),
+            3,
+        );
+        let primary_selection_state = PrimarySelectionState::new::<GlobalState<W>>(&dh);
+        GlobalFilter::TRUSTED.replace_global::<W, ZwpPrimarySelectionDeviceManagerV1>(
+            &dh,
+            primary_selection_state.global(),
+            1,
+        );
+        // inhibiting the key bindings of the wrapper is only offered to trusted clients
+        let keyboard_shortcuts_inhibit_state =
+            KeyboardShortcutsInhibitState::new::<GlobalState<W>>(&dh);
+        GlobalFilter::TRUSTED.replace_global::<W, ZwpKeyboardShortcutsInhibitManagerV1>(
+            &dh,
+            keyboard_shortcuts_inhibit_state.global(),
+            1,
+        );
+
         ServerState {
             popup_manager: PopupManager::default(),
             display_handle: dh.clone(),
@@ -72,14 +141,32 @@ impl<W: WrapperSpace> ServerState<W> {
             seats: Vec::new(),
             compositor_state: CompositorState::new::<GlobalState<W>>(&dh),
             xdg_shell_state: XdgShellState::new::<GlobalState<W>>(&dh),
+            _xdg_decoration_state: XdgDecorationState::new::<GlobalState<W>>(&dh),
+            // for older qt clients which don't support xdg decoration
+            kde_decoration_state: KdeDecorationState::new::<GlobalState<W>>(&dh, KdeMode::Server),
             shm_state: ShmState::new::<GlobalState<W>>(&dh, vec![]),
             _output_manager_state: OutputManagerState::new_with_xdg_output::<GlobalState<W>>(&dh),
             seat_state: SeatState::new(),
-            data_device_state: DataDeviceState::new::<GlobalState<W>>(&dh),
-            primary_selection_state: PrimarySelectionState::new::<GlobalState<W>>(&dh),
-            layer_shell_state: WlrLayerShellState::new::<GlobalState<W>>(&dh),
+            data_device_state,
+            primary_selection_state,
+            // untrusted clients may not place surfaces on the host
+            layer_shell_state: WlrLayerShellState::new_with_filter::<GlobalState<W>, _>(
+                &dh,
+                |client| GlobalFilter::TRUSTED.can_view(client),
+            ),
             _fractional_scale_state: FractionalScaleManagerState::new::<GlobalState<W>>(&dh),
+            keyboard_shortcuts_inhibit_state,
+            _idle_inhibit_state: IdleInhibitManagerState::new::<GlobalState<W>>(&dh),
+            idle_inhibiting_surfaces: Vec::new(),
+            // host activation tokens can raise windows, so untrusted clients may not request them
+            _xdg_activation_global: dh
+                .create_global::<GlobalState<W>, XdgActivationV1, _>(1, GlobalFilter::TRUSTED),
             dmabuf_state: None,
+            dmabuf_feedback_params: None,
+            drm_syncobj_state: None,
+            presentation_state: None,
+            #[cfg(feature = "xwayland")]
+            xwayland_shell_state: XWaylandShellState::new::<GlobalState<W>>(&dh),
         }
     }
 }
@@ -89,6 +176,8 @@ pub(crate) struct ServerSeat<W: WrapperSpace + 'static> {
     pub(crate) selection_source: Option<WlDataSource>,
     pub(crate) dnd_source: Option<WlDataSource>,
     pub(crate) dnd_icon: Option<WlSurface>,
+    /// the client which started the drag
+    pub(crate) dnd_client: Option<ClientId>,
 }
 
 pub(crate) struct SeatPair<W: WrapperSpace + 'static> {
@@ -8,24 +8,56 @@ use sctk::{
     shell::WaylandSurface,
 };
 use smithay::{
-    backend::renderer::{
-        element::surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
-        gles::GlesRenderer,
-        Bind, ImportDma, ImportEgl, Unbind,
+    backend::{
+        allocator::{Format, Fourcc, Modifier},
+        drm::DrmNode,
+        egl::EGLDevice,
+        renderer::{
+            element::surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
+            gles::GlesRenderer,
+            Bind, ImportDma, ImportEgl, Unbind,
+        },
+    },
+    desktop::{
+        utils::{
+            bbox_from_surface_tree, output_update, take_presentation_feedback_surface_tree,
+            OutputPresentationFeedback,
+        },
+        PopupManager,
     },
-    desktop::utils::send_frames_surface_tree,
     output::Output,
-    reexports::wayland_server::{backend::GlobalId, DisplayHandle},
+    reexports::{
+        calloop::{
+            timer::{TimeoutAction, Timer},
+            LoopHandle,
+        },
+        wayland_protocols::wp::{
+            linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1,
+            presentation_time::server::wp_presentation_feedback,
+        },
+        wayland_server::{
+            backend::{ClientId, GlobalId},
+            protocol::wl_surface::WlSurface as s_WlSurface,
+            DisplayHandle,
+        },
+    },
     wayland::{
-        compositor::with_states, dmabuf::DmabufState, fractional_scale::with_fractional_scale,
+        compositor::{get_parent, with_states},
+        dmabuf::{DmabufFeedback, DmabufFeedbackBuilder, DmabufState},
+        fractional_scale::with_fractional_scale,
     },
 };
-use tracing::error;
+use tracing::{error, warn};
 
-use crate::client_state::ClientState;
+use crate::client_state::{ClientMetadata, ClientState};
+use crate::focus::FocusManager;
+use crate::keybindings::KeyBindings;
 use crate::server_state::ServerState;
 use crate::space::WrapperSpace;
 
+/// delay before a replaced dmabuf global is destroyed
+const DMABUF_GLOBAL_REMOVAL_DELAY: Duration = Duration::from_secs(5);
+
 /// group of info for an output
 pub type OutputGroup = (Output, GlobalId, String, c_wl_output::WlOutput);
 
@@ -40,6 +72,17 @@ pub struct GlobalState<W: WrapperSpace + 'static> {
     pub server_state: ServerState<W>,
     /// instant that the panel was started
     pub start_time: std::time::Instant,
+    pub(crate) loop_handle: LoopHandle<'static, GlobalState<W>>,
+    pub(crate) focus_manager: FocusManager,
+    pub(crate) keybindings: KeyBindings,
+    #[cfg(feature = "xwayland")]
+    pub(crate) xwm: Option<smithay::xwayland::X11Wm>,
+    /// mapped X11 windows which were added to the space
+    #[cfg(feature = "xwayland")]
+    pub(crate) x11_windows: Vec<smithay::desktop::Window>,
+    /// the X11 display number of Xwayland, once it is ready
+    #[cfg(feature = "xwayland")]
+    pub xdisplay: Option<u32>,
 }
 
 impl<W: WrapperSpace + 'static> GlobalState<W> {
@@ -48,12 +91,22 @@ impl<W: WrapperSpace + 'static> GlobalState<W> {
         server_state: ServerState<W>,
         space: W,
         start_time: std::time::Instant,
+        loop_handle: LoopHandle<'static, GlobalState<W>>,
     ) -> Self {
         Self {
             space,
             client_state,
             server_state,
             start_time,
+            loop_handle,
+            focus_manager: FocusManager::default(),
+            keybindings: KeyBindings::default(),
+            #[cfg(feature = "xwayland")]
+            xwm: None,
+            #[cfg(feature = "xwayland")]
+            x11_windows: Vec::new(),
+            #[cfg(feature = "xwayland")]
+            xdisplay: None,
         }
     }
 
@@ -86,19 +139,234 @@ impl<W: WrapperSpace + 'static> GlobalState<W> {
 impl<W: WrapperSpace + 'static> GlobalState<W> {
     /// bind the display for the space
     pub fn bind_display(&mut self, dh: &DisplayHandle) {
-        if let Some(renderer) = self.space.renderer() {
-            let res = renderer.bind_wl_display(dh);
-            if let Err(err) = res {
-                error!("{:?}", err);
-            } else {
+        let res = match self.space.renderer() {
+            Some(renderer) => renderer.bind_wl_display(dh),
+            None => return,
+        };
+        if let Err(err) = res {
+            error!("{:?}", err);
+            return;
+        }
+
+        let mut state = DmabufState::new();
+        let params = self.dmabuf_feedback_params();
+        let global = match params.as_ref().and_then(DmabufFeedbackParams::build) {
+            Some(feedback) => {
+                state.create_global_with_default_feedback::<GlobalState<W>>(dh, &feedback)
+            }
+            None => {
+                let Some(renderer) = self.space.renderer() else {
+                    return;
+                };
                 let dmabuf_formats = renderer.dmabuf_formats().into_iter().collect_vec();
-                let mut state = DmabufState::new();
-                let global = state.create_global::<GlobalState<W>>(dh, dmabuf_formats);
-                self.server_state.dmabuf_state.replace((state, global));
+                state.create_global::<GlobalState<W>>(dh, dmabuf_formats)
             }
+        };
+        self.server_state.dmabuf_state.replace((state, global));
+        self.server_state.dmabuf_feedback_params = params;
+
+        self.create_drm_syncobj_state(dh);
+    }
+
+    /// the render node of the space's renderer
+    pub(crate) fn render_node(&mut self) -> Option<DrmNode> {
+        let renderer = self.space.renderer()?;
+        EGLDevice::device_for_display(renderer.egl_context().display())
+            .ok()
+            .and_then(|device| device.try_get_render_node().ok().flatten())
+    }
+
+    /// recreate the embedded dmabuf global with up to date default feedback
+    /// this should be called when the host sends new dmabuf feedback
+    pub(crate) fn update_dmabuf_global(&mut self) {
+        if self.server_state.dmabuf_state.is_none() {
+            // the global is created with the latest feedback when the display is bound
+            return;
+        }
+        let Some(params) = self.dmabuf_feedback_params() else {
+            return;
+        };
+        // the host may resend feedback which doesn't change anything for embedded clients
+        if self.server_state.dmabuf_feedback_params.as_ref() == Some(&params) {
+            return;
+        }
+        let Some(feedback) = params.build() else {
+            return;
+        };
+        self.server_state.dmabuf_feedback_params = Some(params);
+
+        let dh = self.server_state.display_handle.clone();
+        let Some((state, global)) = self.server_state.dmabuf_state.as_mut() else {
+            return;
+        };
+        let new_global =
+            state.create_global_with_default_feedback::<GlobalState<W>>(&dh, &feedback);
+        let old_global = std::mem::replace(global, new_global);
+
+        // disable the old global first, so that clients have time to notice it is gone before it
+        // is destroyed
+        state.disable_global::<GlobalState<W>>(&dh, &old_global);
+        if let Err(err) = self.loop_handle.insert_source(
+            Timer::from_duration(DMABUF_GLOBAL_REMOVAL_DELAY),
+            move |_, _, state| {
+                let dh = state.server_state.display_handle.clone();
+                if let Some((dmabuf_state, _)) = state.server_state.dmabuf_state.as_mut() {
+                    dmabuf_state.destroy_global::<GlobalState<W>>(&dh, old_global.clone());
+                }
+                TimeoutAction::Drop
+            },
+        ) {
+            error!("Failed to schedule removal of dmabuf global: {}", err);
         }
     }
 
+    /// the parameters of the default dmabuf feedback for embedded clients
+    /// the renderer's device is the main device, and the host's tranches are preferred when the
+    /// renderer can import their formats
+    fn dmabuf_feedback_params(&mut self) -> Option<DmabufFeedbackParams> {
+        let render_node = self.render_node();
+        let renderer = self.space.renderer()?;
+        let render_formats = renderer.dmabuf_formats().into_iter().collect_vec();
+
+        let host_feedback = self.client_state.dmabuf_feedback.as_ref();
+        let main_device = match (render_node, host_feedback) {
+            (Some(node), _) => node.dev_id(),
+            (None, Some(host_feedback)) => host_feedback.main_device(),
+            (None, None) => {
+                warn!("Failed to determine the main device for dmabuf feedback");
+                return None;
+            }
+        };
+
+        let mut tranches = Vec::new();
+        if let Some(host_feedback) = host_feedback {
+            let format_table = host_feedback.format_table();
+            for tranche in host_feedback.tranches() {
+                let formats = tranche
+                    .formats
+                    .iter()
+                    .filter_map(|i| format_table.get(*i as usize))
+                    .filter_map(|f| {
+                        Some(Format {
+                            code: Fourcc::try_from(f.format).ok()?,
+                            modifier: Modifier::from(f.modifier),
+                        })
+                    })
+                    .filter(|f| render_formats.contains(f))
+                    .collect_vec();
+                if formats.is_empty() {
+                    continue;
+                }
+                let flags = tranche
+                    .flags
+                    .into_result()
+                    .ok()
+                    .and_then(|flags| {
+                        zwp_linux_dmabuf_feedback_v1::TrancheFlags::from_bits(flags.bits())
+                    })
+                    .filter(|flags| !flags.is_empty());
+                tranches.push((tranche.device, flags, formats));
+            }
+        }
+
+        Some(DmabufFeedbackParams {
+            main_device,
+            render_formats,
+            tranches,
+        })
+    }
+
+    /// send frame callbacks at a low rate to proxied layer surfaces which aren't visible on any
+    /// output, as they won't receive frame callbacks from the host
+    pub(crate) fn send_fallback_frames(&mut self) {
+        let time = self
+            .client_state
+            .host_time()
+            .unwrap_or_else(|| self.start_time.elapsed().as_millis() as u32);
+        for (_, _, s_layer, c_layer, ..) in &self.client_state.proxied_layer_surfaces {
+            if self
+                .client_state
+                .visible_outputs(c_layer.wl_surface())
+                .is_empty()
+            {
+                self.client_state
+                    .send_frames(c_layer.wl_surface(), s_layer.wl_surface(), time);
+            }
+        }
+    }
+
+    /// embedded surfaces displayed by each host surface, including popups and dnd icons
+    pub(crate) fn host_surfaces(&self) -> Vec<(WlSurface, Vec<s_WlSurface>)> {
+        let mut surfaces = self.space.embedded_surfaces();
+        for (_, _, s_layer, c_layer, ..) in &self.client_state.proxied_layer_surfaces {
+            let s_surfaces = std::iter::once(s_layer.wl_surface().clone())
+                .chain(
+                    PopupManager::popups_for_surface(s_layer.wl_surface())
+                        .map(|(popup, _)| popup.wl_surface().clone()),
+                )
+                .collect();
+            surfaces.push((c_layer.wl_surface().clone(), s_surfaces));
+        }
+        for seat in &self.server_state.seats {
+            if let (Some(c_icon), Some(s_icon)) =
+                (seat.client.dnd_icon.as_ref(), seat.server.dnd_icon.as_ref())
+            {
+                surfaces.push((c_icon.1.clone(), vec![s_icon.clone()]));
+            }
+        }
+        surfaces
+    }
+
+    /// the host surface which displays an embedded surface or one of its subsurfaces
+    pub(crate) fn host_surface_of(&self, s_surface: &s_WlSurface) -> Option<WlSurface> {
+        let mut root = s_surface.clone();
+        while let Some(parent) = get_parent(&root) {
+            root = parent;
+        }
+        self.host_surfaces()
+            .into_iter()
+            .find(|(_, s_surfaces)| s_surfaces.contains(&root))
+            .map(|(c_surface, _)| c_surface)
+    }
+
+    /// replicate the outputs which host surfaces are on to the embedded surfaces they display
+    /// embedded surfaces enter an embedded output when their host surface is on the matching host
+    /// output, and leave it otherwise
+    pub(crate) fn update_surface_outputs(&mut self) {
+        for (c_surface, s_surfaces) in self.host_surfaces() {
+            let visible = self.client_state.visible_outputs(&c_surface);
+            for (c_output, s_output, _) in &self.client_state.outputs {
+                let on_output = visible.iter().any(|o| &o.0 == c_output);
+                for s_surface in &s_surfaces {
+                    let overlap = on_output.then(|| bbox_from_surface_tree(s_surface, (0, 0)));
+                    output_update(s_output, overlap, s_surface);
+                }
+            }
+        }
+    }
+
+    /// purge the state of an embedded client which disconnected, and notify the space
+    /// the surfaces of the client were torn down by their destructors already, but state which
+    /// refers to them by client is purged here
+    pub(crate) fn client_disconnected(&mut self, client_id: ClientId, metadata: ClientMetadata) {
+        self.purge_client_focus(&client_id);
+        self.server_state
+            .idle_inhibiting_surfaces
+            .retain(|(_, id)| id != &client_id);
+        self.update_idle_inhibitors();
+        for seat in &mut self.server_state.seats {
+            if seat.server.dnd_client.as_ref() == Some(&client_id) {
+                seat.server.dnd_source = None;
+                seat.server.dnd_icon = None;
+                seat.server.dnd_client = None;
+                seat.client.dnd_source = None;
+                seat.client.dnd_icon = None;
+            }
+        }
+
+        self.space.client_disconnected(client_id, metadata);
+    }
+
     /// draw the dnd icon if it exists and is ready
     pub fn draw_dnd_icon(&mut self) {
         // TODO proxied layer surfaces
@@ -143,6 +411,19 @@ impl<W: WrapperSpace + 'static> GlobalState<W> {
                     *clear_color,
                 )
                 .unwrap();
+            if let (Some(presentation_state), Some(output)) = (
+                self.client_state.presentation_state.as_ref(),
+                self.client_state.presentation_output(wl_surface),
+            ) {
+                let mut feedback = OutputPresentationFeedback::new(&output);
+                take_presentation_feedback_surface_tree(
+                    s_icon,
+                    &mut feedback,
+                    |_, _| Some(output.clone()),
+                    |_, _| wp_presentation_feedback::Kind::empty(),
+                );
+                presentation_state.feedback(wl_surface, feedback, &self.client_state.queue_handle);
+            }
             egl_surface.swap_buffers(None).unwrap();
             // FIXME: damage tracking issues on integrated graphics but not nvidia
             // self.egl_surface
@@ -151,19 +432,39 @@ impl<W: WrapperSpace + 'static> GlobalState<W> {
             //     .swap_buffers(res.0.as_deref_mut())?;
 
             let _ = renderer.unbind();
-            // // TODO what if there is "no output"?
-            for o in &self.client_state.outputs {
-                let output = &o.1;
-                send_frames_surface_tree(
-                    s_icon,
-                    &o.1,
-                    Duration::from_millis(time as u64),
-                    None,
-                    move |_, _| Some(output.clone()),
-                );
-            }
+            self.client_state.send_frames(wl_surface, s_icon, time);
             wl_surface.frame(&self.client_state.queue_handle, wl_surface.clone());
             wl_surface.commit();
         }
     }
 }
+
+/// the parameters the default dmabuf feedback of embedded clients is built from
+/// they are kept to skip rebuilding the global when the host feedback doesn't change them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DmabufFeedbackParams {
+    main_device: libc::dev_t,
+    render_formats: Vec<Format>,
+    tranches: Vec<(
+        libc::dev_t,
+        Option<zwp_linux_dmabuf_feedback_v1::TrancheFlags>,
+        Vec<Format>,
+    )>,
+}
+
+impl DmabufFeedbackParams {
+    /// build the dmabuf feedback
+    fn build(&self) -> Option<DmabufFeedback> {
+        let mut builder = DmabufFeedbackBuilder::new(self.main_device, self.render_formats.clone());
+        for (device, flags, formats) in &self.tranches {
+            builder = builder.add_preference_tranche(*device, *flags, formats.clone());
+        }
+        match builder.build() {
+            Ok(feedback) => Some(feedback),
+            Err(err) => {
+                error!("Failed to build dmabuf feedback: {}", err);
+                None
+            }
+        }
+    }
+}
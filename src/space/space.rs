@@ -2,6 +2,7 @@
 
 use std::{
     cell::RefCell,
+    process::ExitStatus,
     rc::Rc,
     time::{Duration, Instant},
 };
@@ -23,18 +24,32 @@ use smithay::{
     backend::renderer::gles::GlesRenderer,
     desktop::{PopupManager, Window},
     output::Output,
-    reexports::wayland_server::{
-        self, protocol::wl_surface::WlSurface as s_WlSurface, DisplayHandle,
+    reexports::{
+        wayland_protocols::xdg::{
+            decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode,
+            shell::server::xdg_toplevel::ResizeEdge,
+        },
+        wayland_server::{
+            self,
+            backend::ClientId,
+            protocol::{wl_output, wl_surface::WlSurface as s_WlSurface},
+            Client, DisplayHandle,
+        },
     },
-    wayland::shell::xdg::{PopupSurface, PositionerState},
+    utils::{Logical, Point, Size},
+    wayland::shell::xdg::{PopupSurface, PositionerState, ToplevelSurface},
 };
 
 use crate::{
     client::handlers::{
-        wp_fractional_scaling::FractionalScalingManager, wp_viewporter::ViewporterState,
+        wp_fractional_scaling::FractionalScalingManager, wp_presentation::PresentationTimeState,
+        wp_viewporter::ViewporterState,
     },
-    client_state::ClientFocus,
+    client_state::{ClientFocus, ClientMetadata},
     config::WrapperConfig,
+    focus::{FocusPolicy, FocusReason},
+    keybindings::KeyBinding,
+    launcher::ClientLauncher,
     server_state::ServerPointerFocus,
     shared_state::GlobalState,
     wp_security_context::SecurityContextManager,
@@ -89,6 +104,21 @@ impl Default for Visibility {
     }
 }
 
+/// a state change requested by an embedded toplevel
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToplevelRequest {
+    /// maximize the toplevel
+    Maximize,
+    /// unmaximize the toplevel
+    Unmaximize,
+    /// make the toplevel fullscreen, optionally on an embedded output
+    Fullscreen(Option<wl_output::WlOutput>),
+    /// leave fullscreen
+    Unfullscreen,
+    /// minimize the toplevel
+    Minimize,
+}
+
 // TODO break this trait into several traits so that it can be better organized
 // not all "space" implementations really need all of these exact methods as long as they are wrapped by a space that does
 // see cosmic-panel for an example
@@ -109,17 +139,28 @@ pub trait WrapperSpace {
     fn get_client_focused_surface(&self) -> Rc<RefCell<ClientFocus>>;
 
     /// setup of the space after the wayland connection is ready
+    /// the launcher inserts the clients of the space, and may launch and supervise them
     fn setup<W: WrapperSpace>(
         &mut self,
         compositor_state: &CompositorState,
         fractional_scale_manager: Option<&FractionalScalingManager<W>>,
         security_context_manager: Option<SecurityContextManager>,
+        launcher: ClientLauncher<W>,
         viewport: Option<&ViewporterState<W>>,
         layer_state: &mut LayerShell,
         conn: &Connection,
         qh: &QueueHandle<GlobalState<W>>,
     );
 
+    /// the host supports presentation time
+    /// the state may be kept to request feedback for the embedded surfaces rendered by the space
+    /// before each commit of a host surface
+    fn presentation_time_ready<W: WrapperSpace>(
+        &mut self,
+        _presentation_state: &PresentationTimeState<W>,
+    ) {
+    }
+
     /// add the configured output to the space
     fn new_output<W: WrapperSpace>(
         &mut self,
@@ -158,8 +199,13 @@ pub trait WrapperSpace {
     ) -> Option<ServerPointerFocus>;
 
     /// add a top level window to the space
+    /// X11 windows are added once they are mapped, including override redirect windows like menus
     fn add_window(&mut self, s_top_level: Window);
 
+    /// remove a window which was unmapped while it is still alive, like an X11 window
+    /// destroyed windows are no longer alive, and should be removed by the space as well
+    fn remove_window(&mut self, _window: &Window) {}
+
     /// add a popup to the space
     fn add_popup<W: WrapperSpace>(
         &mut self,
@@ -222,7 +268,18 @@ pub trait WrapperSpace {
     /// gets the config
     fn config(&self) -> Self::Config;
 
+    /// sets the config
+    /// called when a new config is applied at runtime, before the outputs are reconfigured
+    /// the config is ignored by default
+    fn set_config(&mut self, _config: Self::Config) {}
+
     /// spawns the clients for the wrapper
+    /// sandboxed applets should be given the connection of a listener from
+    /// [`SecurityContextManager::create_listener`] for the host, and be inserted with
+    /// [`ClientLauncher::get_sandboxed_client_sock`], which keeps the listener alive
+    /// other applets may be classified with [`ClientLauncher::get_client_sock_with_metadata`]
+    /// clients must be inserted with the launcher given in [`WrapperSpace::setup`], so that their
+    /// state is purged when they disconnect
     fn spawn_clients<W: WrapperSpace>(
         &mut self,
         display: wayland_server::DisplayHandle,
@@ -230,6 +287,108 @@ pub trait WrapperSpace {
         security_context_manager: Option<SecurityContextManager>,
     ) -> anyhow::Result<()>;
 
+    /// called when a client launched with a [`ClientLauncher`] exits
+    /// `restart` is the delay after which it is launched again, if its restart policy allows it
+    fn client_exited(&mut self, _id: &str, _status: ExitStatus, _restart: Option<Duration>) {}
+
+    /// called when a client launched with a [`ClientLauncher`] is restarted
+    fn client_restarted(&mut self, _id: &str, _client: Client) {}
+
+    /// called once the state of a disconnected embedded client has been purged from the wrapper
+    /// the space should drop its windows, popups and any other state of the client
+    fn client_disconnected(&mut self, _client_id: ClientId, _metadata: ClientMetadata) {}
+
+    /// how the pointer moves the keyboard focus between embedded surfaces
+    fn focus_policy(&self) -> FocusPolicy {
+        FocusPolicy::default()
+    }
+
+    /// consulted before a click or hover changes the keyboard focus of a seat
+    /// returning false keeps the current focus, ex. to never steal focus from a surface
+    fn allow_focus_change(
+        &mut self,
+        _seat_name: &str,
+        _from: Option<&s_WlSurface>,
+        _to: Option<&s_WlSurface>,
+        _reason: FocusReason,
+    ) -> bool {
+        true
+    }
+
+    /// key bindings which are handled by the space instead of being forwarded to embedded clients
+    fn keybindings(&self) -> Vec<KeyBinding> {
+        self.config().keybindings()
+    }
+
+    /// a key binding was pressed on a seat
+    fn keybinding(&mut self, _seat_name: &str, _binding: &KeyBinding) {}
+
+    /// an embedded client requested activation of one of its surfaces with an xdg activation token
+    /// returning true asks the host to activate the host surface which displays it
+    fn activation_requested(&mut self, _token: &str, _surface: &s_WlSurface) -> bool {
+        false
+    }
+
+    /// the decoration mode of an embedded toplevel, given the mode it requested if any
+    /// server side decorations are the default, so that embedded toplevels don't draw titlebars
+    /// and shadows
+    fn decoration_mode(
+        &mut self,
+        _surface: &s_WlSurface,
+        _requested: Option<DecorationMode>,
+    ) -> DecorationMode {
+        DecorationMode::ServerSide
+    }
+
+    /// an embedded toplevel requested an interactive move on a seat
+    /// returning true starts a move grab on the embedded seat, which reports the pointer offset
+    /// through [`WrapperSpace::toplevel_move`]
+    fn toplevel_move_request(&mut self, _seat_name: &str, _toplevel: &ToplevelSurface) -> bool {
+        false
+    }
+
+    /// the pointer moved during a move grab, by an offset from where the grab started
+    fn toplevel_move(&mut self, _toplevel: &ToplevelSurface, _offset: Point<i32, Logical>) {}
+
+    /// an embedded toplevel requested an interactive resize on a seat
+    /// returning true starts a resize grab on the embedded seat, which configures the toplevel
+    /// with sizes within its size constraints and reports them through
+    /// [`WrapperSpace::toplevel_resize`]
+    fn toplevel_resize_request(
+        &mut self,
+        _seat_name: &str,
+        _toplevel: &ToplevelSurface,
+        _edges: ResizeEdge,
+    ) -> bool {
+        false
+    }
+
+    /// the toplevel was configured with a new size during a resize grab
+    /// when resizing from the top or left edges, the space should move the toplevel so that the
+    /// opposite edges stay in place
+    fn toplevel_resize(
+        &mut self,
+        _toplevel: &ToplevelSurface,
+        _edges: ResizeEdge,
+        _size: Size<i32, Logical>,
+    ) {
+    }
+
+    /// a move or resize grab of a toplevel ended
+    fn toplevel_grab_ended(&mut self, _toplevel: &ToplevelSurface) {}
+
+    /// an embedded toplevel requested a state change
+    /// the pending state of the toplevel may be changed, and is sent to it in a configure after
+    /// this returns
+    fn toplevel_state_request(&mut self, _toplevel: &ToplevelSurface, _request: ToplevelRequest) {}
+
+    /// the parent of an embedded toplevel changed
+    fn toplevel_parent_changed(&mut self, _toplevel: &ToplevelSurface) {}
+
+    /// Xwayland is ready, X11 clients may connect to the display
+    #[cfg(feature = "xwayland")]
+    fn xwayland_ready(&mut self, _display: u32) {}
+
     /// gets visibility of the wrapper
     fn visibility(&self) -> Visibility {
         Visibility::Visible
@@ -259,6 +418,11 @@ pub trait WrapperSpace {
     /// finished popup
     fn close_popup(&mut self, popup: &sctk::shell::xdg::popup::Popup);
 
+    /// embedded popups were destroyed, ordered from the deepest child to the destroyed popup
+    /// the host popups of each of them must be dropped immediately, in that order, as the host
+    /// only allows destroying the topmost popup
+    fn popup_destroyed(&mut self, _popups: &[PopupSurface]) {}
+
     /// configure layer
     fn configure_layer(&mut self, layer: &LayerSurface, configure: LayerSurfaceConfigure);
 
@@ -292,6 +456,19 @@ pub trait WrapperSpace {
     /// returns none if the surface is not tracked by this space
     fn get_scale_factor(&self, surface: &s_WlSurface) -> Option<f64>;
 
+    /// embedded surfaces displayed by each host surface of the space, including popups
+    /// used to send output enter and leave events to embedded surfaces, matching the outputs which
+    /// their host surface is on
+    fn embedded_surfaces(&self) -> Vec<(wl_surface::WlSurface, Vec<s_WlSurface>)> {
+        Vec::new()
+    }
+
+    /// host xdg windows of the space, and the embedded windows they display
+    /// the configures and close requests of each host window are relayed to its embedded window
+    fn proxied_windows(&self) -> Vec<(sctk::shell::xdg::window::Window, Window)> {
+        Vec::new()
+    }
+
     /// Generate Pointer events for clients
     fn generate_pointer_events(&mut self) -> Vec<PointerEvent> {
         Vec::new()
@@ -1,12 +1,12 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::{
-    io::{BufWriter, Write},
-    os::unix::net::UnixStream,
-    sync::Arc,
-};
+use std::io::{BufWriter, Write};
 
-use smithay::reexports::wayland_server::{self, Client};
+use smithay::{
+    desktop::Window,
+    reexports::wayland_server::{protocol::wl_surface::WlSurface as s_WlSurface, Client, Resource},
+    wayland::shell::xdg::PopupSurface,
+};
 // SPDX-License-Identifier: MPL-2.0
 use anyhow::{bail, Result};
 use sctk::{
@@ -21,7 +21,7 @@ use smithay::{
     },
 };
 
-use crate::client_state::WrapperClientCompositorState;
+use crate::client_state::{ClientMetadata, WrapperClientCompositorState};
 
 use super::WrapperSpace;
 
@@ -30,21 +30,27 @@ pub fn smootherstep(t: f32) -> f32 {
     (6.0 * t.powi(5) - 15.0 * t.powi(4) + 10.0 * t.powi(3)).clamp(0.0, 1.0)
 }
 
-/// helper function for inserting a wrapped applet client
-pub fn get_client_sock(display: &mut wayland_server::DisplayHandle) -> (Client, UnixStream) {
-    let (display_sock, client_sock) = UnixStream::pair().unwrap();
+/// metadata of an embedded client
+/// clients which weren't inserted by the wrapper have no metadata
+pub fn client_metadata(client: &Client) -> Option<ClientMetadata> {
+    client
+        .get_data::<WrapperClientCompositorState>()
+        .map(|data| data.metadata.clone())
+}
+
+/// metadata of the client which owns an embedded surface
+pub fn surface_metadata(surface: &s_WlSurface) -> Option<ClientMetadata> {
+    surface.client().as_ref().and_then(client_metadata)
+}
+
+/// metadata of the client which owns an embedded window
+pub fn window_metadata(window: &Window) -> Option<ClientMetadata> {
+    surface_metadata(&window.wl_surface()?)
+}
 
-    (
-        display
-            .insert_client(
-                display_sock,
-                Arc::new(WrapperClientCompositorState {
-                    compositor_state: Default::default(),
-                }),
-            )
-            .unwrap(),
-        client_sock,
-    )
+/// metadata of the client which owns an embedded popup
+pub fn popup_metadata(popup: &PopupSurface) -> Option<ClientMetadata> {
+    surface_metadata(popup.wl_surface())
 }
 
 pub(crate) fn write_and_attach_buffer<W: WrapperSpace + 'static>(
@@ -0,0 +1,334 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::{os::fd::OwnedFd, process::Stdio};
+
+use sctk::data_device_manager::data_offer::receive_to_fd;
+use smithay::{
+    delegate_xwayland_shell,
+    desktop::Window,
+    reexports::wayland_server::Resource,
+    utils::{Logical, Rectangle},
+    wayland::{
+        selection::{
+            data_device::{
+                clear_data_device_selection, current_data_device_selection_userdata,
+                request_data_device_client_selection, set_data_device_selection,
+            },
+            primary_selection::{
+                clear_primary_selection, current_primary_selection_userdata,
+                request_primary_client_selection, set_primary_selection,
+            },
+            SelectionTarget,
+        },
+        xwayland_shell::{XWaylandShellHandler, XWaylandShellState},
+    },
+    xwayland::{
+        xwm::{Reorder, ResizeEdge, XwmId},
+        X11Surface, X11Wm, XWayland, XWaylandClientData, XWaylandEvent,
+    },
+};
+use tracing::{error, info, warn};
+
+use crate::{server::handlers::SelectionOrigin, shared_state::GlobalState, space::WrapperSpace};
+
+impl<W: WrapperSpace + 'static> GlobalState<W> {
+    /// start Xwayland on the embedded display, and its window manager once it is ready
+    pub(crate) fn start_xwayland(&mut self) {
+        let dh = self.server_state.display_handle.clone();
+        let (xwayland, client) = match XWayland::spawn(
+            &dh,
+            None,
+            std::iter::empty::<(String, String)>(),
+            true,
+            Stdio::null(),
+            Stdio::null(),
+            |_| {},
+        ) {
+            Ok(res) => res,
+            Err(err) => {
+                error!("Failed to start Xwayland: {}", err);
+                return;
+            }
+        };
+
+        if let Err(err) = self
+            .loop_handle
+            .insert_source(xwayland, move |event, _, state| match event {
+                XWaylandEvent::Ready {
+                    x11_socket,
+                    display_number,
+                } => match X11Wm::start_wm(state.loop_handle.clone(), x11_socket, client.clone()) {
+                    Ok(wm) => {
+                        info!("Xwayland is ready on :{}", display_number);
+                        state.xwm = Some(wm);
+                        state.xdisplay = Some(display_number);
+                        state.space.xwayland_ready(display_number);
+                    }
+                    Err(err) => error!("Failed to start the X11 window manager: {}", err),
+                },
+                XWaylandEvent::Error => {
+                    warn!("Xwayland exited");
+                    state.xwm = None;
+                    state.xdisplay = None;
+                    for window in std::mem::take(&mut state.x11_windows) {
+                        state.space.remove_window(&window);
+                    }
+                }
+            })
+        {
+            error!("Failed to insert the Xwayland source: {}", err);
+        }
+    }
+
+    /// offer a new selection of the embedded seats to X11 clients
+    pub(crate) fn x11_new_selection(
+        &mut self,
+        target: SelectionTarget,
+        mime_types: Option<Vec<String>>,
+    ) {
+        if let Some(xwm) = self.xwm.as_mut() {
+            if let Err(err) = xwm.new_selection(target, mime_types) {
+                warn!(?err, "Failed to set the X11 selection");
+            }
+        }
+    }
+
+    /// write the current X11 selection to a file descriptor
+    pub(crate) fn x11_send_selection(
+        &mut self,
+        target: SelectionTarget,
+        mime_type: String,
+        fd: OwnedFd,
+    ) {
+        let loop_handle = self.loop_handle.clone();
+        if let Some(xwm) = self.xwm.as_mut() {
+            if let Err(err) = xwm.send_selection(target, mime_type, fd, loop_handle) {
+                warn!(?err, "Failed to send the X11 selection");
+            }
+        }
+    }
+
+    /// add a mapped X11 window to the space
+    fn add_x11_window(&mut self, surface: X11Surface) {
+        let window = Window::new_x11_window(surface);
+        self.x11_windows.push(window.clone());
+        self.space.add_window(window);
+    }
+
+    /// remove an unmapped or destroyed X11 window from the space
+    fn remove_x11_window(&mut self, surface: &X11Surface) {
+        let Some(i) = self
+            .x11_windows
+            .iter()
+            .position(|w| w.x11_surface() == Some(surface))
+        else {
+            return;
+        };
+        let window = self.x11_windows.remove(i);
+        self.space.remove_window(&window);
+    }
+
+    /// whether the keyboard of an embedded seat is on an X11 window
+    fn x11_has_keyboard_focus(&self) -> bool {
+        self.server_state.seats.iter().any(|seat| {
+            self.focus_manager
+                .focused(&seat.name)
+                .and_then(|s| s.client())
+                .is_some_and(|c| c.get_data::<XWaylandClientData>().is_some())
+        })
+    }
+}
+
+impl<W: WrapperSpace + 'static> smithay::xwayland::XwmHandler for GlobalState<W> {
+    fn xwm_state(&mut self, _xwm: XwmId) -> &mut X11Wm {
+        self.xwm.as_mut().unwrap()
+    }
+
+    fn new_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn new_override_redirect_window(&mut self, _xwm: XwmId, _window: X11Surface) {}
+
+    fn map_window_request(&mut self, _xwm: XwmId, window: X11Surface) {
+        if let Err(err) = window.set_mapped(true) {
+            warn!(?err, "Failed to map X11 window");
+            return;
+        }
+        self.add_x11_window(window);
+    }
+
+    fn mapped_override_redirect_window(&mut self, _xwm: XwmId, window: X11Surface) {
+        self.add_x11_window(window);
+    }
+
+    fn unmapped_window(&mut self, _xwm: XwmId, window: X11Surface) {
+        self.remove_x11_window(&window);
+        if !window.is_override_redirect() {
+            let _ = window.set_mapped(false);
+        }
+    }
+
+    fn destroyed_window(&mut self, _xwm: XwmId, window: X11Surface) {
+        self.remove_x11_window(&window);
+    }
+
+    fn configure_request(
+        &mut self,
+        _xwm: XwmId,
+        window: X11Surface,
+        x: Option<i32>,
+        y: Option<i32>,
+        w: Option<u32>,
+        h: Option<u32>,
+        _reorder: Option<Reorder>,
+    ) {
+        // the requested geometry is granted, the space may configure the window again once it is
+        // mapped
+        let mut geometry: Rectangle<i32, Logical> = window.geometry();
+        if let Some(x) = x {
+            geometry.loc.x = x;
+        }
+        if let Some(y) = y {
+            geometry.loc.y = y;
+        }
+        if let Some(w) = w {
+            geometry.size.w = w as i32;
+        }
+        if let Some(h) = h {
+            geometry.size.h = h as i32;
+        }
+        let _ = window.configure(geometry);
+    }
+
+    fn configure_notify(
+        &mut self,
+        _xwm: XwmId,
+        _window: X11Surface,
+        _geometry: Rectangle<i32, Logical>,
+        _above: Option<u32>,
+    ) {
+    }
+
+    fn resize_request(
+        &mut self,
+        _xwm: XwmId,
+        _window: X11Surface,
+        _button: u32,
+        _resize_edge: ResizeEdge,
+    ) {
+        // interactive grabs are only supported for xdg toplevels
+    }
+
+    fn move_request(&mut self, _xwm: XwmId, _window: X11Surface, _button: u32) {
+        // interactive grabs are only supported for xdg toplevels
+    }
+
+    fn allow_selection_access(&mut self, _xwm: XwmId, _selection: SelectionTarget) -> bool {
+        // X11 clients may only read the clipboard while one of them has keyboard focus
+        self.x11_has_keyboard_focus()
+    }
+
+    fn send_selection(
+        &mut self,
+        _xwm: XwmId,
+        selection: SelectionTarget,
+        mime_type: String,
+        fd: OwnedFd,
+    ) {
+        // X11 has a single seat
+        let Some(seat) = self.server_state.seats.first() else {
+            return;
+        };
+        let s_seat = &seat.server.seat;
+        let res = match selection {
+            SelectionTarget::Clipboard => {
+                let origin = current_data_device_selection_userdata::<Self>(s_seat).map(|o| *o);
+                match origin {
+                    // the selection is owned by an embedded client
+                    None => request_data_device_client_selection(s_seat, mime_type, fd),
+                    Some(SelectionOrigin::Host) => {
+                        if let Some(offer) = seat.client.selection_offer.as_ref() {
+                            unsafe { receive_to_fd(offer.inner(), mime_type, fd) }
+                        }
+                        Ok(())
+                    }
+                    Some(SelectionOrigin::Xwayland) => Ok(()),
+                }
+            }
+            SelectionTarget::Primary => {
+                let origin = current_primary_selection_userdata::<Self>(s_seat).map(|o| *o);
+                match origin {
+                    None => request_primary_client_selection(s_seat, mime_type, fd),
+                    Some(_) => Ok(()),
+                }
+            }
+        };
+        if let Err(err) = res {
+            warn!(?err, "Failed to request the selection for X11");
+        }
+    }
+
+    fn new_selection(&mut self, _xwm: XwmId, selection: SelectionTarget, mime_types: Vec<String>) {
+        let dh = self.server_state.display_handle.clone();
+        let Some(seat) = self.server_state.seats.first_mut() else {
+            return;
+        };
+        match selection {
+            SelectionTarget::Clipboard => {
+                set_data_device_selection(
+                    &dh,
+                    &seat.server.seat,
+                    mime_types.clone(),
+                    SelectionOrigin::Xwayland,
+                );
+                // the host clipboard is set as well, as if an embedded client had set it
+                let serial = seat.client.get_serial_of_last_seat_event();
+                seat.client.next_selection_offer_is_mine = true;
+                let copy_paste_source = self
+                    .client_state
+                    .data_device_manager
+                    .create_copy_paste_source(&self.client_state.queue_handle, mime_types);
+                copy_paste_source.set_selection(&seat.client.data_device, serial);
+                seat.client.copy_paste_source = Some(copy_paste_source);
+            }
+            SelectionTarget::Primary => {
+                set_primary_selection(
+                    &dh,
+                    &seat.server.seat,
+                    mime_types,
+                    SelectionOrigin::Xwayland,
+                );
+            }
+        }
+    }
+
+    fn cleared_selection(&mut self, _xwm: XwmId, selection: SelectionTarget) {
+        let dh = self.server_state.display_handle.clone();
+        for seat in &self.server_state.seats {
+            let seat = &seat.server.seat;
+            match selection {
+                SelectionTarget::Clipboard => {
+                    if current_data_device_selection_userdata::<Self>(seat).as_deref()
+                        == Some(&SelectionOrigin::Xwayland)
+                    {
+                        clear_data_device_selection(&dh, seat);
+                    }
+                }
+                SelectionTarget::Primary => {
+                    if current_primary_selection_userdata::<Self>(seat).as_deref()
+                        == Some(&SelectionOrigin::Xwayland)
+                    {
+                        clear_primary_selection(&dh, seat);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<W: WrapperSpace + 'static> XWaylandShellHandler for GlobalState<W> {
+    fn xwayland_shell_state(&mut self) -> &mut XWaylandShellState {
+        &mut self.server_state.xwayland_shell_state
+    }
+}
+
+delegate_xwayland_shell!(@<W: WrapperSpace + 'static> GlobalState<W>);
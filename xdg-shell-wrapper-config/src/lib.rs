@@ -77,10 +77,241 @@ impl Into<zwlr_layer_surface_v1::KeyboardInteractivity> for KeyboardInteractivit
 pub enum WrapperOutput {
     All,
     Name(Vec<String>),
+    /// Outputs which match any of the included rules and none of the excluded rules
+    Match {
+        include: Vec<OutputMatch>,
+        #[serde(default)]
+        exclude: Vec<OutputMatch>,
+    },
+    /// The first output advertised by the compositor
+    Primary,
+}
+
+impl WrapperOutput {
+    /// Whether the output should be used by the wrapper
+    pub fn matches(&self, output: &OutputIdentity<'_>, is_primary: bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Name(list) => list.iter().any(|name| name == output.name),
+            Self::Match { include, exclude } => {
+                include.iter().any(|rule| rule.matches(output))
+                    && !exclude.iter().any(|rule| rule.matches(output))
+            }
+            Self::Primary => is_primary,
+        }
+    }
+}
+
+/// Rule for matching an output
+/// All patterns are globs, where `*` matches any sequence of characters and `?` matches a single character
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub enum OutputMatch {
+    /// Connector name, ex. `DP-*`
+    Name(String),
+    /// Monitor identity, which is stable across connectors. Unset fields match any value.
+    Monitor {
+        #[serde(default)]
+        make: Option<String>,
+        #[serde(default)]
+        model: Option<String>,
+        /// Never matches when the host doesn't advertise the serial number of the output, which
+        /// wl_output doesn't.
+        #[serde(default)]
+        serial: Option<String>,
+    },
+    /// Human readable description of the output
+    Description(String),
+}
+
+impl OutputMatch {
+    /// Whether the output matches this rule
+    pub fn matches(&self, output: &OutputIdentity<'_>) -> bool {
+        match self {
+            Self::Name(pattern) => glob_match(pattern, output.name),
+            Self::Monitor {
+                make,
+                model,
+                serial,
+            } => {
+                make.as_ref()
+                    .map_or(true, |pattern| glob_match(pattern, output.make))
+                    && model
+                        .as_ref()
+                        .map_or(true, |pattern| glob_match(pattern, output.model))
+                    && serial.as_ref().map_or(true, |pattern| {
+                        output
+                            .serial
+                            .is_some_and(|serial| glob_match(pattern, serial))
+                    })
+            }
+            Self::Description(pattern) => glob_match(pattern, output.description),
+        }
+    }
+}
+
+/// Identity of an output, as advertised by the compositor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputIdentity<'a> {
+    /// Connector name
+    pub name: &'a str,
+    /// Monitor make
+    pub make: &'a str,
+    /// Monitor model
+    pub model: &'a str,
+    /// Monitor serial number, if advertised by the host
+    pub serial: Option<&'a str>,
+    /// Human readable description
+    pub description: &'a str,
+}
+
+/// Match a string against a glob pattern, where `*` matches any sequence of characters and `?`
+/// matches a single character
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let s: Vec<char> = s.chars().collect();
+    let (mut p, mut i) = (0, 0);
+    // position of the last `*` in the pattern, and the position in the string it was tried at
+    let mut backtrack = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_i)) => {
+                    // let the last `*` consume one more character
+                    p = star + 1;
+                    i = star_i + 1;
+                    backtrack = Some((star, star_i + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Modifiers which must be held for a key binding
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Default, PartialEq, Eq)]
+pub struct KeyModifiers {
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub alt: bool,
+    #[serde(default)]
+    pub shift: bool,
+    /// Super or logo key
+    #[serde(default)]
+    pub logo: bool,
+}
+
+/// Key binding which is handled by the wrapper instead of being forwarded to embedded clients
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    /// Modifiers which must be held, and no others
+    #[serde(default)]
+    pub modifiers: KeyModifiers,
+    /// Name of the keysym, ex. `Escape` or `Super_L`
+    pub key: String,
+    /// Action which is passed to the wrapper when the binding is pressed
+    pub action: String,
 }
 
 pub trait WrapperConfig: Clone + fmt::Debug + Default {
     fn outputs(&self) -> WrapperOutput;
 
     fn name(&self) -> &str;
+
+    /// Key bindings handled by the wrapper
+    fn keybindings(&self) -> Vec<KeyBinding> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: OutputIdentity<'static> = OutputIdentity {
+        name: "DP-1",
+        make: "Dell Inc.",
+        model: "DELL U2720Q",
+        serial: Some("ABC123"),
+        description: "Dell Inc. DELL U2720Q ABC123 (DP-1)",
+    };
+
+    #[test]
+    fn glob_star() {
+        assert!(glob_match("DP-*", "DP-1"));
+        assert!(glob_match("DP-*", "DP-"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*-*-1", "HDMI-A-1"));
+        assert!(!glob_match("DP-*", "eDP-1"));
+    }
+
+    #[test]
+    fn glob_question_mark() {
+        assert!(glob_match("DP-?", "DP-1"));
+        assert!(!glob_match("DP-?", "DP-"));
+        assert!(!glob_match("DP-?", "DP-10"));
+    }
+
+    #[test]
+    fn glob_empty_pattern() {
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "DP-1"));
+    }
+
+    #[test]
+    fn glob_no_match() {
+        assert!(!glob_match("HDMI-A-1", "DP-1"));
+        assert!(!glob_match("DP-1", "DP-10"));
+        assert!(!glob_match("DP-10", "DP-1"));
+    }
+
+    #[test]
+    fn match_name() {
+        assert!(OutputMatch::Name("DP-*".into()).matches(&OUTPUT));
+        assert!(!OutputMatch::Name("HDMI-*".into()).matches(&OUTPUT));
+    }
+
+    #[test]
+    fn match_monitor() {
+        let monitor =
+            |make: Option<&str>, model: Option<&str>, serial: Option<&str>| OutputMatch::Monitor {
+                make: make.map(String::from),
+                model: model.map(String::from),
+                serial: serial.map(String::from),
+            };
+        assert!(monitor(None, None, None).matches(&OUTPUT));
+        assert!(monitor(Some("Dell*"), Some("*U2720Q"), Some("ABC???")).matches(&OUTPUT));
+        assert!(!monitor(Some("LG*"), None, None).matches(&OUTPUT));
+        assert!(!monitor(None, Some("U2720Q"), None).matches(&OUTPUT));
+        assert!(!monitor(None, None, Some("XYZ*")).matches(&OUTPUT));
+    }
+
+    #[test]
+    fn match_monitor_serial_not_advertised() {
+        let output = OutputIdentity {
+            serial: None,
+            ..OUTPUT
+        };
+        let rule = OutputMatch::Monitor {
+            make: Some("Dell*".into()),
+            model: None,
+            serial: Some("ABC*".into()),
+        };
+        assert!(!rule.matches(&output));
+    }
+
+    #[test]
+    fn match_description() {
+        assert!(OutputMatch::Description("Dell Inc. *".into()).matches(&OUTPUT));
+        assert!(!OutputMatch::Description("Dell Inc.".into()).matches(&OUTPUT));
+    }
 }